@@ -0,0 +1,45 @@
+// The cost of one HostTimeFilter update with a full window, which should not depend on
+// the size of the window.
+//
+// Usage:
+//   cargo bench --bench host_time_filter
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use rusty_link::{FilterWindow, HostTimeFilter, HostTimeFilterConfig};
+use std::hint::black_box;
+
+const BUFFER_SIZE: u64 = 256;
+const SAMPLE_RATE: u64 = 48_000;
+
+fn host_time(sample_clock: u64) -> i64 {
+    (sample_clock * 1_000_000 / SAMPLE_RATE) as i64 + 1_000_000
+}
+
+fn sample_time_to_host_time(c: &mut Criterion) {
+    let mut group = c.benchmark_group("sample_time_to_host_time");
+    for window in [64, 256, 1024, 4096] {
+        let mut filter = HostTimeFilter::with_config(HostTimeFilterConfig {
+            window: FilterWindow::Points(window),
+            ..HostTimeFilterConfig::default()
+        });
+        let mut sample_clock = 0;
+        for _ in 0..window {
+            sample_clock += BUFFER_SIZE;
+            filter.sample_time_to_host_time(host_time(sample_clock), sample_clock);
+        }
+
+        group.bench_function(BenchmarkId::from_parameter(window), |b| {
+            b.iter(|| {
+                sample_clock += BUFFER_SIZE;
+                filter.sample_time_to_host_time(
+                    black_box(host_time(sample_clock)),
+                    black_box(sample_clock),
+                )
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sample_time_to_host_time);
+criterion_main!(benches);
@@ -0,0 +1,110 @@
+// Compares the host time estimators with a synthetic audio device.
+//
+// The device runs 80 ppm faster than its nominal sample rate, its callbacks are called
+// with random jitter and occasional late spikes, and after half of the run its latency
+// jumps by 2 ms, like USB interfaces sometimes do. Each estimator is fed the same
+// callbacks, and its error against the true host time of the sample clock is reported.
+//
+// Usage:
+//   cargo run --release --features test-util --example host_time_jitter -- [regression] [dll] [kalman]
+
+use rusty_link::{
+    DelayLockedLoop, HostTimeEstimator, HostTimeFilter, KalmanFilter,
+    test_util::{SyntheticDevice, SyntheticDeviceConfig},
+};
+use std::env;
+
+const SAMPLE_RATE: f64 = 48_000.;
+const SECONDS: f64 = 60.;
+// Errors are only counted after this time, so the warm-up does not dominate them
+const WARM_UP_SECONDS: f64 = 5.;
+const SETTLED_MICROS: f64 = 200.;
+
+fn device() -> SyntheticDeviceConfig {
+    SyntheticDeviceConfig {
+        sample_rate: SAMPLE_RATE,
+        spike_micros: 3000.,
+        spike_every: 500,
+        jump_micros: 2000.,
+        jump_seconds: SECONDS / 2.,
+        ..Default::default()
+    }
+}
+
+fn estimator(name: &str) -> Box<dyn HostTimeEstimator> {
+    match name {
+        "regression" => Box::new(HostTimeFilter::new()),
+        "dll" => Box::new(DelayLockedLoop::new(SAMPLE_RATE)),
+        "kalman" => Box::new(KalmanFilter::new(SAMPLE_RATE)),
+        _ => panic!("unknown estimator {name}, use regression, dll or kalman"),
+    }
+}
+
+#[derive(Default)]
+struct Stats {
+    count: u64,
+    sum_squares: f64,
+    max: f64,
+    // Time from the latency jump until the error is below SETTLED_MICROS again
+    settle_seconds: Option<f64>,
+}
+
+fn run(estimator: &mut dyn HostTimeEstimator) -> Stats {
+    let config = device();
+    let mut stats = Stats::default();
+    for callback in SyntheticDevice::new(config).take_while(|callback| callback.seconds <= SECONDS)
+    {
+        let estimate =
+            estimator.sample_time_to_host_time(callback.clock_micros, callback.sample_clock);
+        let error = (estimate as f64 - callback.true_micros).abs();
+
+        if callback.seconds >= WARM_UP_SECONDS {
+            stats.count += 1;
+            stats.sum_squares += error * error;
+            stats.max = stats.max.max(error);
+        }
+        if callback.seconds >= config.jump_seconds
+            && stats.settle_seconds.is_none()
+            && error < SETTLED_MICROS
+        {
+            stats.settle_seconds = Some(callback.seconds - config.jump_seconds);
+        }
+    }
+    stats
+}
+
+fn main() {
+    let mut names: Vec<String> = env::args().skip(1).collect();
+    if names.is_empty() {
+        names = vec!["regression".into(), "dll".into(), "kalman".into()];
+    }
+
+    let config = device();
+    println!(
+        "{} Hz, {} samples, {} ppm fast, {} µs jitter, {} µs spikes, {} µs jump after {} s",
+        config.sample_rate,
+        config.buffer_size,
+        config.rate_error_ppm,
+        config.jitter_micros,
+        config.spike_micros,
+        config.jump_micros,
+        config.jump_seconds
+    );
+    println!(
+        "{:<12} {:>10} {:>10} {:>12}",
+        "estimator", "rms µs", "max µs", "settle s"
+    );
+    for name in &names {
+        let stats = run(&mut estimator(name));
+        println!(
+            "{:<12} {:>10.1} {:>10.1} {:>12}",
+            name,
+            (stats.sum_squares / stats.count as f64).sqrt(),
+            stats.max,
+            match stats.settle_seconds {
+                Some(seconds) => format!("{seconds:.2}"),
+                None => "never".to_string(),
+            }
+        );
+    }
+}
@@ -1,6 +1,8 @@
 use crate::{audio_platform_cpal::AudioPlatformCpal, input_thread::UpdateSessionState};
 use cpal::Stream;
-use rusty_link::{AblLink, HostTimeFilter, SessionState};
+use rusty_link::{
+    AudioThreadHandle, HostTimeEstimator, HostTimeFilter, MAX_TEMPO, MIN_TEMPO, SessionState,
+};
 use std::{
     cmp::Ordering,
     f32::consts::TAU,
@@ -19,7 +21,7 @@ pub struct AudioEngine {
 
 impl AudioEngine {
     pub fn new(
-        link: Arc<AblLink>,
+        link: AudioThreadHandle,
         audio_cpal: AudioPlatformCpal,
         input: Receiver<UpdateSessionState>,
         quantum: Arc<Mutex<f64>>,
@@ -38,8 +40,7 @@ impl AudioEngine {
                                     sample_time: Duration,
                                     sample_clock: u64| {
             // Update time and other variables:
-            let invoke_time =
-                host_time_filter.sample_time_to_host_time(link.clock_micros(), sample_clock);
+            let invoke_time = host_time_filter.sample_time_to_host_time_with(&link, sample_clock);
 
             let invoke_time_as_duration = Duration::from_micros(invoke_time.try_into().unwrap());
 
@@ -55,22 +56,26 @@ impl AudioEngine {
             if let Ok(command) = input.try_recv() {
                 match command {
                     UpdateSessionState::TempoPlus => {
-                        audio_session_state
-                            .set_tempo((audio_session_state.tempo() + 1.).min(999.), invoke_time);
+                        audio_session_state.set_tempo(
+                            (audio_session_state.tempo() + 1.).min(MAX_TEMPO),
+                            invoke_time,
+                        );
                         link.commit_audio_session_state(&audio_session_state);
                     }
                     UpdateSessionState::TempoMinus => {
-                        audio_session_state
-                            .set_tempo((audio_session_state.tempo() - 1.).max(20.), invoke_time);
+                        audio_session_state.set_tempo(
+                            (audio_session_state.tempo() - 1.).max(MIN_TEMPO),
+                            invoke_time,
+                        );
                         link.commit_audio_session_state(&audio_session_state);
                     }
                     UpdateSessionState::TogglePlaying => {
                         if audio_session_state.is_playing() {
-                            audio_session_state.set_is_playing(false, invoke_time as i64);
+                            audio_session_state.set_is_playing(false, invoke_time);
                         } else {
                             audio_session_state.set_is_playing_and_request_beat_at_time(
                                 true,
-                                invoke_time as i64,
+                                invoke_time,
                                 0.,
                                 last_known_quantum,
                             );
@@ -2,7 +2,7 @@ use crossterm::{
     event::{self, Event, KeyCode},
     terminal,
 };
-use rusty_link::AblLink;
+use rusty_link::AppHandle;
 use std::sync::{
     atomic::{AtomicBool, Ordering},
     mpsc, Arc, Mutex,
@@ -21,7 +21,7 @@ pub enum UpdateSessionState {
 pub fn poll_input(
     tx: mpsc::Sender<UpdateSessionState>,
     running: Arc<AtomicBool>,
-    link: Arc<AblLink>,
+    link: AppHandle,
     quantum: Arc<Mutex<f64>>,
 ) {
     terminal::enable_raw_mode().unwrap();
@@ -40,9 +40,11 @@ fn main() {
     println!("\nenabled | num peers | quantum | start stop sync | tempo   | beats    | metro");
 
     // Init Multithread Variables
-    let abl_link = Arc::new(AblLink::new(120.));
-    let abl_link_clone_input_thread = Arc::clone(&abl_link);
-    let abl_link_clone_audio_thread = Arc::clone(&abl_link);
+    let abl_link = AblLink::new(120.);
+    let app_handle_input_thread = abl_link.app_handle();
+    let audio_thread_handle = abl_link
+        .audio_thread_handle()
+        .expect("No other audio thread handle exists yet");
 
     let running = Arc::new(AtomicBool::new(true));
     let running_clone_input_thread = Arc::clone(&running);
@@ -57,14 +59,14 @@ fn main() {
         input_thread::poll_input(
             input_tx,
             running_clone_input_thread,
-            abl_link_clone_input_thread,
+            app_handle_input_thread,
             quantum_clone_input_thread,
         );
     });
 
     // Init Audio Engine
     let mut audio_engine = AudioEngine::new(
-        abl_link_clone_audio_thread,
+        audio_thread_handle,
         audio_platform,
         input_rx,
         quantum_clone_audio_thread,
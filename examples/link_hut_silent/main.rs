@@ -10,7 +10,7 @@ use crossterm::{
     style::Print,
     terminal,
 };
-use rusty_link::{AblLink, SessionState};
+use rusty_link::{AblLink, MAX_TEMPO, MIN_TEMPO, SessionState};
 use std::{
     io::{self, Write},
     time::Duration,
@@ -112,13 +112,13 @@ fn poll_input(state: &mut State, duration: Duration) -> io::Result<()> {
                 KeyCode::Char('w') => {
                     state
                         .session_state
-                        .set_tempo((tempo - 1.).max(20.), time_stamp);
+                        .set_tempo((tempo - 1.).max(MIN_TEMPO), time_stamp);
                     state.commit_app_state();
                 }
                 KeyCode::Char('e') => {
                     state
                         .session_state
-                        .set_tempo((tempo + 1.).min(999.), time_stamp);
+                        .set_tempo((tempo + 1.).min(MAX_TEMPO), time_stamp);
                     state.commit_app_state();
                 }
 
@@ -134,11 +134,11 @@ fn poll_input(state: &mut State, duration: Duration) -> io::Result<()> {
                 // Play / Stop Toggle
                 KeyCode::Char(' ') => {
                     if state.session_state.is_playing() {
-                        state.session_state.set_is_playing(false, time_stamp as i64);
+                        state.session_state.set_is_playing(false, time_stamp);
                     } else {
                         state.session_state.set_is_playing_and_request_beat_at_time(
                             true,
-                            time_stamp as i64,
+                            time_stamp,
                             0.,
                             state.quantum,
                         );
@@ -0,0 +1,273 @@
+// Measures how well several Link peers in this process stay in sync.
+//
+// The peers join one after another. For each join, the time until all peers see each other
+// and agree on the phase is measured. Afterwards the beat and phase of every peer are
+// sampled at the same Link clock times, and compared with the first peer.
+//
+// Usage:
+//   cargo run --release --example sync_analyzer -- [--peers 4] [--seconds 10]
+//       [--interval-ms 10] [--quantum 4] [--csv samples.csv] [--json report.json]
+
+use rusty_link::{AblLink, SessionState};
+use std::{
+    env,
+    fmt::Write as _,
+    fs,
+    str::FromStr,
+    thread,
+    time::{Duration, Instant},
+};
+
+// The phase error below which a joined peer counts as converged.
+const CONVERGED_MICROS: f64 = 1000.;
+const CONVERGENCE_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Config {
+    peers: usize,
+    seconds: f64,
+    interval: Duration,
+    quantum: f64,
+    csv: Option<String>,
+    json: Option<String>,
+}
+
+impl Config {
+    fn from_args() -> Config {
+        let mut config = Config {
+            peers: 4,
+            seconds: 10.,
+            interval: Duration::from_millis(10),
+            quantum: 4.,
+            csv: None,
+            json: None,
+        };
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let value = args
+                .next()
+                .unwrap_or_else(|| panic!("missing value for {arg}"));
+            match arg.as_str() {
+                "--peers" => config.peers = parse(&arg, &value),
+                "--seconds" => config.seconds = parse(&arg, &value),
+                "--interval-ms" => config.interval = Duration::from_millis(parse(&arg, &value)),
+                "--quantum" => config.quantum = parse(&arg, &value),
+                "--csv" => config.csv = Some(value),
+                "--json" => config.json = Some(value),
+                _ => panic!("unknown argument {arg}"),
+            }
+        }
+        assert!(config.peers >= 2, "at least 2 peers are needed");
+        assert!(
+            config.quantum.is_finite() && config.quantum > 0.,
+            "the quantum must be positive and finite"
+        );
+        config
+    }
+}
+
+fn parse<T: FromStr>(arg: &str, value: &str) -> T {
+    value
+        .parse()
+        .unwrap_or_else(|_| panic!("invalid value {value} for {arg}"))
+}
+
+struct Sample {
+    elapsed_micros: i64,
+    peer: usize,
+    beat: f64,
+    phase: f64,
+    error_micros: f64,
+}
+
+/// The beat, phase and tempo of each peer at the given time.
+fn measure(peers: &[AblLink], time: i64, quantum: f64) -> Vec<(f64, f64, f64)> {
+    let mut session_state = SessionState::new();
+    peers
+        .iter()
+        .map(|peer| {
+            peer.capture_app_session_state(&mut session_state);
+            (
+                session_state.beat_at_time(time, quantum),
+                session_state.phase_at_time(time, quantum),
+                session_state.tempo(),
+            )
+        })
+        .collect()
+}
+
+/// The phase error of a peer compared with the reference peer in microseconds, taking
+/// the shortest way around the quantum.
+fn phase_error_micros(phase: f64, reference: f64, tempo: f64, quantum: f64) -> f64 {
+    let mut difference = (phase - reference).rem_euclid(quantum);
+    if difference > quantum / 2. {
+        difference -= quantum;
+    }
+    difference * 60e6 / tempo
+}
+
+fn percentile(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return 0.;
+    }
+    let index = ((sorted.len() - 1) as f64 * p).round() as usize;
+    sorted[index]
+}
+
+/// A JSON number with three decimals. JSON has no NaN or infinity, so these are written
+/// as null.
+fn json_number(value: f64) -> String {
+    if value.is_finite() {
+        format!("{value:.3}")
+    } else {
+        "null".to_string()
+    }
+}
+
+/// Slope of a least squares line through the points, in y units per x unit.
+fn slope(points: &[(f64, f64)]) -> f64 {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let covariance: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let variance: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    if variance == 0. {
+        0.
+    } else {
+        covariance / variance
+    }
+}
+
+fn main() {
+    let config = Config::from_args();
+
+    // Different initial tempos, so the joining peers have to adopt the session tempo.
+    let peers: Vec<AblLink> = (0..config.peers)
+        .map(|i| AblLink::new(120. + i as f64))
+        .collect();
+
+    // JOIN AND CONVERGENCE
+    let mut convergence = Vec::new();
+    peers[0].enable(true);
+    for joined in 1..config.peers {
+        let start = Instant::now();
+        peers[joined].enable(true);
+        let active = &peers[..=joined];
+        let converged = loop {
+            let all_connected = active.iter().all(|peer| peer.num_peers() == joined as u64);
+            let time = peers[0].clock_micros();
+            let values = measure(active, time, config.quantum);
+            let in_phase = values.iter().all(|&(_, phase, tempo)| {
+                phase_error_micros(phase, values[0].1, tempo, config.quantum).abs()
+                    < CONVERGED_MICROS
+            });
+            if all_connected && in_phase {
+                break Some(start.elapsed());
+            }
+            if start.elapsed() > CONVERGENCE_TIMEOUT {
+                break None;
+            }
+            thread::sleep(Duration::from_millis(1));
+        };
+        match converged {
+            Some(duration) => println!("peer {joined} converged after {duration:?}"),
+            None => println!("peer {joined} did not converge within {CONVERGENCE_TIMEOUT:?}"),
+        }
+        convergence.push(converged);
+    }
+
+    // SAMPLING
+    let mut samples = Vec::new();
+    let start_micros = peers[0].clock_micros();
+    let end_micros = start_micros + (config.seconds * 1e6) as i64;
+    println!("sampling {} peers for {} s", config.peers, config.seconds);
+    loop {
+        // All instances in one process share the same clock.
+        let time = peers[0].clock_micros();
+        if time > end_micros {
+            break;
+        }
+        let values = measure(&peers, time, config.quantum);
+        for (peer, &(beat, phase, tempo)) in values.iter().enumerate() {
+            samples.push(Sample {
+                elapsed_micros: time - start_micros,
+                peer,
+                beat,
+                phase,
+                error_micros: phase_error_micros(phase, values[0].1, tempo, config.quantum),
+            });
+        }
+        thread::sleep(config.interval);
+    }
+
+    // REPORT
+    let mut errors: Vec<f64> = samples
+        .iter()
+        .filter(|sample| sample.peer != 0)
+        .map(|sample| sample.error_micros.abs())
+        .collect();
+    errors.sort_by(f64::total_cmp);
+    let mean = errors.iter().sum::<f64>() / errors.len().max(1) as f64;
+
+    // Drift of each peer against the first peer, in microseconds per minute.
+    let drift: Vec<f64> = (1..config.peers)
+        .map(|peer| {
+            let points: Vec<(f64, f64)> = samples
+                .iter()
+                .filter(|sample| sample.peer == peer)
+                .map(|sample| (sample.elapsed_micros as f64 / 60e6, sample.error_micros))
+                .collect();
+            slope(&points)
+        })
+        .collect();
+
+    println!("phase error |µs|: mean {mean:.1}");
+    for p in [0.5, 0.95, 0.99, 1.] {
+        println!("  p{:<3} {:.1}", p * 100., percentile(&errors, p));
+    }
+    for (i, drift) in drift.iter().enumerate() {
+        println!("drift of peer {}: {drift:.3} µs/min", i + 1);
+    }
+
+    if let Some(path) = &config.csv {
+        let mut csv = String::from("elapsed_micros,peer,beat,phase,error_micros\n");
+        for s in &samples {
+            writeln!(
+                csv,
+                "{},{},{},{},{}",
+                s.elapsed_micros, s.peer, s.beat, s.phase, s.error_micros
+            )
+            .unwrap();
+        }
+        fs::write(path, csv).expect("failed to write the CSV file");
+        println!("wrote {path}");
+    }
+
+    if let Some(path) = &config.json {
+        let convergence_millis: Vec<String> = convergence
+            .iter()
+            .map(|c| match c {
+                Some(duration) => json_number(duration.as_secs_f64() * 1e3),
+                None => "null".to_string(),
+            })
+            .collect();
+        let drift: Vec<String> = drift.iter().map(|&d| json_number(d)).collect();
+        let json = format!(
+            "{{\n  \"peers\": {},\n  \"quantum\": {},\n  \"samples\": {},\n  \
+             \"convergence_millis\": [{}],\n  \"phase_error_micros\": {{ \"mean\": {}, \
+             \"p50\": {}, \"p95\": {}, \"p99\": {}, \"max\": {} }},\n  \
+             \"drift_micros_per_minute\": [{}]\n}}\n",
+            config.peers,
+            config.quantum,
+            samples.len(),
+            convergence_millis.join(", "),
+            json_number(mean),
+            json_number(percentile(&errors, 0.5)),
+            json_number(percentile(&errors, 0.95)),
+            json_number(percentile(&errors, 0.99)),
+            json_number(percentile(&errors, 1.)),
+            drift.join(", "),
+        );
+        fs::write(path, json).expect("failed to write the JSON file");
+        println!("wrote {path}");
+    }
+}
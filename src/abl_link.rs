@@ -1,30 +1,313 @@
-use crate::{rust_bindings::*, session_state::SessionState, split};
-use std::os::raw::c_void;
+#[cfg(feature = "rt-check")]
+use crate::rt_check;
+use crate::{Error, Micros, error, rust_bindings::*, session_state::SessionState, split};
+use std::{
+    any::Any,
+    os::raw::c_void,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        Arc, Mutex, PoisonError, Weak,
+        atomic::{AtomicBool, AtomicU8, AtomicU64, Ordering},
+    },
+};
 
 /// The representation of an abl_link instance.
 pub struct AblLink {
+    pub(crate) inner: Arc<LinkInner>,
+}
+
+/// The abl_link instance and its callback dispatchers. Shared with all [Subscription]s,
+/// so that they can unregister their callback from any thread.
+pub(crate) struct LinkInner {
     pub(crate) link: abl_link,
+    // Set while an AudioThreadHandle exists.
+    pub(crate) audio_thread_handle_taken: AtomicBool,
+    next_id: AtomicU64,
+    panic_policy: Arc<AtomicU8>,
+    num_peers: Arc<Dispatcher<u64>>,
+    tempo: Arc<Dispatcher<f64>>,
+    start_stop: Arc<Dispatcher<bool>>,
+    // The closures registered with the C side, which forward to the dispatchers. They
+    // are boxed, so that the pointers handed to abl_link stay valid until it is destroyed.
+    _entry_points: [Box<dyn Send>; 3],
 }
 
-unsafe impl Send for AblLink {}
-unsafe impl Sync for AblLink {}
+unsafe impl Send for LinkInner {}
+unsafe impl Sync for LinkInner {}
 
-impl Drop for AblLink {
+impl Drop for LinkInner {
     fn drop(&mut self) {
+        // The entry points and all registered closures are only dropped after this,
+        // once the Link threads, which could still call them, are gone.
         unsafe { abl_link_destroy(self.link) }
     }
 }
 
+impl LinkInner {
+    fn remove_callback(&self, kind: CallbackKind, id: CallbackId) -> bool {
+        match kind {
+            CallbackKind::NumPeers => self.num_peers.remove(id),
+            CallbackKind::Tempo => self.tempo.remove(id),
+            CallbackKind::StartStop => self.start_stop.remove(id),
+        }
+    }
+}
+
+/// What happens after a registered callback panicked. Callbacks are called from a
+/// Link-managed C++ thread, so a panic is always caught before it reaches the C side.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum PanicPolicy {
+    /// Print the panic message to stderr and keep the callback registered.
+    Log,
+    /// Print the panic message to stderr and unregister the callback.
+    #[default]
+    Unregister,
+    /// Abort the process.
+    Abort,
+}
+
+impl PanicPolicy {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            0 => PanicPolicy::Log,
+            1 => PanicPolicy::Unregister,
+            _ => PanicPolicy::Abort,
+        }
+    }
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "Box<dyn Any>"
+    }
+}
+
+/// abl_link only supports a single callback per event. The Dispatcher is registered once
+/// as that callback and fans each notification out to any number of Rust callbacks.
+///
+/// Callbacks are invoked in the order they were registered. A callback added while a
+/// notification is being delivered is first invoked for the next notification. A callback
+/// removed while a notification is being delivered is not invoked anymore, unless its
+/// invocation had already started. Its closure is dropped once that invocation returned.
+/// A panic in a callback is handled according to the [PanicPolicy].
+struct Dispatcher<T> {
+    name: &'static str,
+    listeners: Mutex<Vec<Arc<Listener<T>>>>,
+    panic_policy: Arc<AtomicU8>,
+}
+
+struct Listener<T> {
+    id: CallbackId,
+    active: AtomicBool,
+    closure: Mutex<Box<dyn FnMut(T) + Send>>,
+}
+
+impl<T: Copy> Dispatcher<T> {
+    fn new(name: &'static str, panic_policy: &Arc<AtomicU8>) -> Self {
+        Self {
+            name,
+            listeners: Mutex::new(Vec::new()),
+            panic_policy: Arc::clone(panic_policy),
+        }
+    }
+
+    fn add(&self, id: CallbackId, closure: Box<dyn FnMut(T) + Send>) {
+        self.listeners.lock().unwrap().push(Arc::new(Listener {
+            id,
+            active: AtomicBool::new(true),
+            closure: Mutex::new(closure),
+        }));
+    }
+
+    fn remove(&self, id: CallbackId) -> bool {
+        let mut listeners = self.listeners.lock().unwrap();
+        match listeners.iter().position(|l| l.id == id) {
+            Some(index) => {
+                listeners
+                    .remove(index)
+                    .active
+                    .store(false, Ordering::Release);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn clear(&self) {
+        for listener in self.listeners.lock().unwrap().drain(..) {
+            listener.active.store(false, Ordering::Release);
+        }
+    }
+
+    fn notify(&self, value: T) {
+        // Deliver to a copy of the list, so that callbacks can add or remove
+        // callbacks without deadlocking.
+        let listeners = self.listeners.lock().unwrap().clone();
+        for listener in listeners {
+            if !listener.active.load(Ordering::Acquire) {
+                continue;
+            }
+
+            // The closure is not used anymore after a panic with the Unregister policy,
+            // and the Log policy explicitly asks to keep using it, so a poisoned lock is fine.
+            let mut closure = listener
+                .closure
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let Err(payload) = panic::catch_unwind(AssertUnwindSafe(|| closure(value))) else {
+                continue;
+            };
+            drop(closure);
+
+            let message = panic_message(payload.as_ref());
+            match PanicPolicy::from_u8(self.panic_policy.load(Ordering::Relaxed)) {
+                PanicPolicy::Log => {
+                    eprintln!("rusty_link: {} callback panicked: {message}", self.name)
+                }
+                PanicPolicy::Unregister => {
+                    eprintln!(
+                        "rusty_link: {} callback panicked and was unregistered: {message}",
+                        self.name
+                    );
+                    self.remove(listener.id);
+                }
+                PanicPolicy::Abort => {
+                    eprintln!(
+                        "rusty_link: {} callback panicked, aborting: {message}",
+                        self.name
+                    );
+                    std::process::abort();
+                }
+            }
+        }
+    }
+}
+
+/// Registers a closure forwarding to `dispatcher` with the C side, by passing its split
+/// parts to `set_callback`. The returned box has to outlive the abl_link instance.
+fn register_dispatcher<T: Copy + Send + 'static>(
+    dispatcher: &Arc<Dispatcher<T>>,
+    set_callback: impl FnOnce(Option<unsafe extern "C" fn(T, *mut c_void)>, *mut c_void),
+) -> Box<dyn Send> {
+    let dispatcher = Arc::clone(dispatcher);
+    let mut entry_point = Box::new(move |value: T| dispatcher.notify(value));
+    unsafe {
+        let (state, callback) = split::split_closure_trailing_data(&mut *entry_point);
+        set_callback(Some(callback), state);
+    }
+    entry_point
+}
+
+/// Identifies a callback registered on an [AblLink].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct CallbackId(u64);
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum CallbackKind {
+    NumPeers,
+    Tempo,
+    StartStop,
+}
+
+/// A guard for a callback registered on an [AblLink]. The callback is unregistered
+/// when the guard is dropped.
+///
+/// A Subscription can be dropped from any thread and may outlive the [AblLink]
+/// it was created by.
+#[must_use = "the callback is unregistered immediately if the Subscription is dropped"]
+pub struct Subscription {
+    inner: Weak<LinkInner>,
+    kind: CallbackKind,
+    id: CallbackId,
+}
+
+impl Subscription {
+    /// The id of the registered callback, which can be passed to [AblLink::remove_callback].
+    pub fn id(&self) -> CallbackId {
+        self.id
+    }
+
+    /// Keep the callback registered until it is removed, deleted, or the
+    /// [AblLink] is dropped.
+    pub fn detach(self) {
+        std::mem::forget(self);
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        if let Some(inner) = self.inner.upgrade() {
+            inner.remove_callback(self.kind, self.id);
+        }
+    }
+}
+
 impl AblLink {
     ///  Construct a new AblLink instance with an initial tempo.
     ///
     ///  Thread-safe: yes
     ///
     ///  Realtime-safe: no
+    ///
+    ///  Link clamps the tempo to the range it supports. Panics, if abl_link fails to
+    ///  create the instance. See [AblLink::try_new] for a fallible version.
     pub fn new(bpm: f64) -> AblLink {
-        AblLink {
-            link: unsafe { abl_link_create(bpm) },
+        Self::create(bpm).expect("Failed to create abl_link instance.")
+    }
+
+    ///  Construct a new AblLink instance with an initial tempo.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: no
+    ///
+    ///  Returns [Error::InvalidTempo], if the tempo is not finite or outside of the range
+    ///  Link supports, and [Error::LinkCreationFailed], if abl_link fails to create the
+    ///  instance.
+    pub fn try_new(bpm: f64) -> Result<AblLink, Error> {
+        Self::create(error::validate_tempo(bpm)?)
+    }
+
+    fn create(bpm: f64) -> Result<AblLink, Error> {
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::new");
+        let link = unsafe { abl_link_create(bpm) };
+        if link.impl_.is_null() {
+            return Err(Error::LinkCreationFailed);
         }
+        let panic_policy = Arc::new(AtomicU8::new(PanicPolicy::default() as u8));
+        let num_peers = Arc::new(Dispatcher::new("num_peers", &panic_policy));
+        let tempo = Arc::new(Dispatcher::new("tempo", &panic_policy));
+        let start_stop = Arc::new(Dispatcher::new("start_stop", &panic_policy));
+        let entry_points = [
+            register_dispatcher(&num_peers, |callback, state| unsafe {
+                abl_link_set_num_peers_callback(link, callback, state)
+            }),
+            register_dispatcher(&tempo, |callback, state| unsafe {
+                abl_link_set_tempo_callback(link, callback, state)
+            }),
+            register_dispatcher(&start_stop, |callback, state| unsafe {
+                abl_link_set_start_stop_callback(link, callback, state)
+            }),
+        ];
+
+        Ok(AblLink {
+            inner: Arc::new(LinkInner {
+                link,
+                audio_thread_handle_taken: AtomicBool::new(false),
+                next_id: AtomicU64::new(0),
+                panic_policy,
+                num_peers,
+                tempo,
+                start_stop,
+                _entry_points: entry_points,
+            }),
+        })
     }
 
     ///  Is Link currently enabled?
@@ -33,7 +316,7 @@ impl AblLink {
     ///
     ///  Realtime-safe: yes
     pub fn is_enabled(&self) -> bool {
-        unsafe { abl_link_is_enabled(self.link) }
+        unsafe { abl_link_is_enabled(self.inner.link) }
     }
 
     ///  Enable/disable Link.
@@ -42,7 +325,9 @@ impl AblLink {
     ///
     ///  Realtime-safe: no
     pub fn enable(&self, enable: bool) {
-        unsafe { abl_link_enable(self.link, enable) }
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::enable");
+        unsafe { abl_link_enable(self.inner.link, enable) }
     }
 
     ///  Is start/stop synchronization enabled?
@@ -51,7 +336,9 @@ impl AblLink {
     ///
     ///  Realtime-safe: no
     pub fn is_start_stop_sync_enabled(&self) -> bool {
-        unsafe { abl_link_is_start_stop_sync_enabled(self.link) }
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::is_start_stop_sync_enabled");
+        unsafe { abl_link_is_start_stop_sync_enabled(self.inner.link) }
     }
 
     ///  Enable start/stop synchronization.
@@ -60,7 +347,9 @@ impl AblLink {
     ///
     ///  Realtime-safe: no
     pub fn enable_start_stop_sync(&self, enable: bool) {
-        unsafe { abl_link_enable_start_stop_sync(self.link, enable) }
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::enable_start_stop_sync");
+        unsafe { abl_link_enable_start_stop_sync(self.inner.link, enable) }
     }
 
     ///  How many peers are currently connected in a Link session?
@@ -69,7 +358,7 @@ impl AblLink {
     ///
     ///  Realtime-safe: yes
     pub fn num_peers(&self) -> u64 {
-        unsafe { abl_link_num_peers(self.link) }
+        unsafe { abl_link_num_peers(self.inner.link) }
     }
 
     /// Get the current link clock time in microseconds.
@@ -78,21 +367,16 @@ impl AblLink {
     ///
     ///  Realtime-safe: yes
     pub fn clock_micros(&self) -> i64 {
-        unsafe { abl_link_clock_micros(self.link) }
+        unsafe { abl_link_clock_micros(self.inner.link) }
     }
 
-    ///  Capture the current Link Session State from the audio thread.
+    /// Get the current link clock time. Typed variant of [AblLink::clock_micros].
     ///
-    ///  Thread-safe: no
+    ///  Thread-safe: yes
     ///
     ///  Realtime-safe: yes
-    ///
-    ///  This function should ONLY be called in the audio thread and must not be
-    ///  accessed from any other threads. After capturing the SessionState holds a snapshot
-    ///  of the current Link Session State, so it should be used in a local scope. The
-    ///  session_state should not be created on the audio thread.
-    pub fn capture_audio_session_state(&self, session_state: &mut SessionState) {
-        unsafe { abl_link_capture_audio_session_state(self.link, session_state.session_state) }
+    pub fn clock(&self) -> Micros {
+        Micros::new(self.clock_micros())
     }
 
     /// Capture the current Link Session State from an application thread.
@@ -106,20 +390,7 @@ impl AblLink {
     ///  contains a snapshot of the current Link state, so it should be used in a local
     ///  scope.
     pub fn capture_app_session_state(&self, session_state: &mut SessionState) {
-        unsafe { abl_link_capture_app_session_state(self.link, session_state.session_state) };
-    }
-
-    ///  Commit the given Session State to the Link session from the audio thread.
-    ///
-    ///  Thread-safe: no
-    ///
-    ///  Realtime-safe: yes
-    ///
-    ///  This function should ONLY be called in the audio thread. The given
-    ///  session_state will replace the current Link state. Modifications will be
-    ///  communicated to other peers in the session.
-    pub fn commit_audio_session_state(&self, session_state: &SessionState) {
-        unsafe { abl_link_commit_audio_session_state(self.link, session_state.session_state) };
+        unsafe { abl_link_capture_app_session_state(self.inner.link, session_state.session_state) };
     }
 
     ///  Commit the given Session State to the Link session from an application thread.
@@ -132,7 +403,9 @@ impl AblLink {
     ///  Modifications of the Session State will be communicated to other peers in the
     ///  session.
     pub fn commit_app_session_state(&self, session_state: &SessionState) {
-        unsafe { abl_link_commit_app_session_state(self.link, session_state.session_state) };
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::commit_app_session_state");
+        unsafe { abl_link_commit_app_session_state(self.inner.link, session_state.session_state) };
     }
 
     ///  Register a callback to be notified when the number of
@@ -142,12 +415,18 @@ impl AblLink {
     ///
     ///  Realtime-safe: no
     ///
-    ///  The callback is invoked on a Link-managed thread.
-    pub fn set_num_peers_callback<C: FnMut(u64) + Send + 'static>(&self, mut closure: C) {
-        unsafe {
-            let (state, callback) = split::split_closure_trailing_data(&mut closure);
-            abl_link_set_num_peers_callback(self.link, Some(callback), state);
-        };
+    ///  The callback is invoked on a Link-managed thread, after all previously registered
+    ///  callbacks of the same kind. It stays registered until the returned [Subscription]
+    ///  is dropped.
+    pub fn set_num_peers_callback<C: FnMut(u64) + Send + 'static>(
+        &self,
+        closure: C,
+    ) -> Subscription {
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::set_num_peers_callback");
+        let id = self.next_callback_id();
+        self.inner.num_peers.add(id, Box::new(closure));
+        self.subscription(CallbackKind::NumPeers, id)
     }
 
     ///  Register a callback to be notified when the session tempo changes.
@@ -156,12 +435,15 @@ impl AblLink {
     ///
     ///  Realtime-safe: no
     ///
-    ///  The callback is invoked on a Link-managed thread.
-    pub fn set_tempo_callback<C: FnMut(f64) + Send + 'static>(&self, mut closure: C) {
-        unsafe {
-            let (state, callback) = split::split_closure_trailing_data(&mut closure);
-            abl_link_set_tempo_callback(self.link, Some(callback), state);
-        }
+    ///  The callback is invoked on a Link-managed thread, after all previously registered
+    ///  callbacks of the same kind. It stays registered until the returned [Subscription]
+    ///  is dropped.
+    pub fn set_tempo_callback<C: FnMut(f64) + Send + 'static>(&self, closure: C) -> Subscription {
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::set_tempo_callback");
+        let id = self.next_callback_id();
+        self.inner.tempo.add(id, Box::new(closure));
+        self.subscription(CallbackKind::Tempo, id)
     }
 
     ///  Register a callback to be notified when the state of start/stop isPlaying changes.
@@ -170,59 +452,402 @@ impl AblLink {
     ///
     ///  Realtime-safe: no
     ///
-    ///  The callback is invoked on a Link-managed thread.
-    pub fn set_start_stop_callback<C: FnMut(bool) + Send + 'static>(&self, mut closure: C) {
-        unsafe {
-            let (state, callback) = split::split_closure_trailing_data(&mut closure);
-            abl_link_set_start_stop_callback(self.link, Some(callback), state);
-        }
+    ///  The callback is invoked on a Link-managed thread, after all previously registered
+    ///  callbacks of the same kind. It stays registered until the returned [Subscription]
+    ///  is dropped.
+    pub fn set_start_stop_callback<C: FnMut(bool) + Send + 'static>(
+        &self,
+        closure: C,
+    ) -> Subscription {
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::set_start_stop_callback");
+        let id = self.next_callback_id();
+        self.inner.start_stop.add(id, Box::new(closure));
+        self.subscription(CallbackKind::StartStop, id)
+    }
+
+    ///  Remove a single callback, which was registered with one of the `set_..._callback`
+    ///  functions. Returns false, if no callback with this id is registered.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: no
+    pub fn remove_callback(&self, id: CallbackId) -> bool {
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::remove_callback");
+        [
+            CallbackKind::NumPeers,
+            CallbackKind::Tempo,
+            CallbackKind::StartStop,
+        ]
+        .into_iter()
+        .any(|kind| self.inner.remove_callback(kind, id))
     }
 
-    ///  Delete the callback which notifies when the number of peers in the Link session changes.
+    ///  Delete all callbacks which notify when the number of peers in the Link session changes.
     ///
     ///  Thread-safe: yes
     ///
     ///  Realtime-safe: no
     pub fn delete_num_peers_callback(&self) {
-        extern "C" fn empty_fn(_: u64, _: *mut c_void) {}
-        unsafe {
-            abl_link_set_num_peers_callback(
-                self.link,
-                Some(empty_fn),
-                std::ptr::null_mut() as *mut c_void,
-            );
-        }
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::delete_num_peers_callback");
+        self.inner.num_peers.clear();
     }
 
-    ///  Delete the callback which notifies when the session tempo changes.
+    ///  Delete all callbacks which notify when the session tempo changes.
     ///
     ///  Thread-safe: yes
     ///
     ///  Realtime-safe: no
     pub fn delete_tempo_callback(&self) {
-        extern "C" fn empty_fn(_: f64, _: *mut c_void) {}
-        unsafe {
-            abl_link_set_tempo_callback(
-                self.link,
-                Some(empty_fn),
-                std::ptr::null_mut() as *mut c_void,
-            );
-        }
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::delete_tempo_callback");
+        self.inner.tempo.clear();
     }
 
-    ///  Delete the callback which notifies when the state of start/stop isPlaying changes.
+    ///  Delete all callbacks which notify when the state of start/stop isPlaying changes.
     ///
     ///  Thread-safe: yes
     ///
     ///  Realtime-safe: no
     pub fn delete_start_stop_callback(&self) {
-        extern "C" fn empty_fn(_: bool, _: *mut c_void) {}
-        unsafe {
-            abl_link_set_start_stop_callback(
-                self.link,
-                Some(empty_fn),
-                std::ptr::null_mut() as *mut c_void,
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::delete_start_stop_callback");
+        self.inner.start_stop.clear();
+    }
+
+    ///  Set what happens after a registered callback panicked. Defaults to
+    ///  [PanicPolicy::Unregister].
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: yes
+    pub fn set_callback_panic_policy(&self, policy: PanicPolicy) {
+        self.inner
+            .panic_policy
+            .store(policy as u8, Ordering::Relaxed);
+    }
+
+    fn next_callback_id(&self) -> CallbackId {
+        CallbackId(self.inner.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn subscription(&self, kind: CallbackKind, id: CallbackId) -> Subscription {
+        Subscription {
+            inner: Arc::downgrade(&self.inner),
+            kind,
+            id,
+        }
+    }
+}
+
+// Deliver notifications like the Link-managed thread does.
+#[cfg(test)]
+impl AblLink {
+    pub(crate) fn notify_num_peers(&self, num_peers: u64) {
+        self.inner.num_peers.notify(num_peers);
+    }
+
+    pub(crate) fn notify_tempo(&self, tempo: f64) {
+        self.inner.tempo.notify(tempo);
+    }
+
+    pub(crate) fn notify_start_stop(&self, is_playing: bool) {
+        self.inner.start_stop.notify(is_playing);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process::Command, sync::atomic::AtomicUsize};
+
+    const ABORT_CHILD_VAR: &str = "RUSTY_LINK_ABORT_CHILD";
+
+    // Counts how often the closure, which owns it, was dropped.
+    struct DropCounter(Arc<AtomicUsize>);
+
+    impl Drop for DropCounter {
+        fn drop(&mut self) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn counted() -> (DropCounter, Arc<AtomicUsize>) {
+        let drops = Arc::new(AtomicUsize::new(0));
+        (DropCounter(Arc::clone(&drops)), drops)
+    }
+
+    fn dispatcher() -> Arc<Dispatcher<u64>> {
+        Arc::new(Dispatcher::new(
+            "test",
+            &Arc::new(AtomicU8::new(PanicPolicy::default() as u8)),
+        ))
+    }
+
+    #[test]
+    fn closure_lives_as_long_as_its_subscription() {
+        let link = AblLink::new(120.);
+        let (counter, drops) = counted();
+        let subscription = link.set_tempo_callback(move |_| {
+            let _ = &counter;
+        });
+        assert_eq!(drops.load(Ordering::SeqCst), 0);
+        drop(subscription);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn registering_another_callback_keeps_the_first_alive() {
+        let link = AblLink::new(120.);
+        let (first_counter, first_drops) = counted();
+        let (second_counter, second_drops) = counted();
+        let first = link.set_num_peers_callback(move |_| {
+            let _ = &first_counter;
+        });
+        let second = link.set_num_peers_callback(move |_| {
+            let _ = &second_counter;
+        });
+        assert_eq!(first_drops.load(Ordering::SeqCst), 0);
+
+        assert!(link.remove_callback(first.id()));
+        assert_eq!(first_drops.load(Ordering::SeqCst), 1);
+        assert_eq!(second_drops.load(Ordering::SeqCst), 0);
+        drop(first);
+        drop(second);
+        assert_eq!(first_drops.load(Ordering::SeqCst), 1);
+        assert_eq!(second_drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn delete_drops_all_callbacks_of_the_kind() {
+        let link = AblLink::new(120.);
+        let (tempo_counter, tempo_drops) = counted();
+        let (start_stop_counter, start_stop_drops) = counted();
+        link.set_tempo_callback(move |_| {
+            let _ = &tempo_counter;
+        })
+        .detach();
+        link.set_start_stop_callback(move |_| {
+            let _ = &start_stop_counter;
+        })
+        .detach();
+
+        link.delete_tempo_callback();
+        assert_eq!(tempo_drops.load(Ordering::SeqCst), 1);
+        assert_eq!(start_stop_drops.load(Ordering::SeqCst), 0);
+        link.delete_start_stop_callback();
+        assert_eq!(start_stop_drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn dropping_the_link_drops_detached_callbacks() {
+        let link = AblLink::new(120.);
+        let (counter, drops) = counted();
+        link.set_start_stop_callback(move |_| {
+            let _ = &counter;
+        })
+        .detach();
+        drop(link);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn subscription_can_outlive_the_link() {
+        let link = AblLink::new(120.);
+        let (counter, drops) = counted();
+        let subscription = link.set_tempo_callback(move |_| {
+            let _ = &counter;
+        });
+        drop(link);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        drop(subscription);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn subscription_can_be_dropped_on_another_thread() {
+        let link = AblLink::new(120.);
+        let (counter, drops) = counted();
+        let subscription = link.set_num_peers_callback(move |_| {
+            let _ = &counter;
+        });
+        let id = subscription.id();
+        std::thread::spawn(move || drop(subscription))
+            .join()
+            .unwrap();
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert!(!link.remove_callback(id));
+    }
+
+    #[test]
+    fn delivers_in_registration_order() {
+        let dispatcher = dispatcher();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        for id in 0..3 {
+            let calls = Arc::clone(&calls);
+            dispatcher.add(
+                CallbackId(id),
+                Box::new(move |value| calls.lock().unwrap().push((id, value))),
             );
         }
+        dispatcher.notify(7);
+        assert_eq!(*calls.lock().unwrap(), [(0, 7), (1, 7), (2, 7)]);
+    }
+
+    #[test]
+    fn callback_added_during_notify_is_called_from_the_next_notify() {
+        let dispatcher = dispatcher();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let weak = Arc::downgrade(&dispatcher);
+        let adder_calls = Arc::clone(&calls);
+        let mut added = false;
+        dispatcher.add(
+            CallbackId(0),
+            Box::new(move |value| {
+                adder_calls.lock().unwrap().push((0, value));
+                if !added {
+                    added = true;
+                    let calls = Arc::clone(&adder_calls);
+                    weak.upgrade().unwrap().add(
+                        CallbackId(1),
+                        Box::new(move |value| calls.lock().unwrap().push((1, value))),
+                    );
+                }
+            }),
+        );
+
+        dispatcher.notify(1);
+        assert_eq!(*calls.lock().unwrap(), [(0, 1)]);
+        dispatcher.notify(2);
+        assert_eq!(*calls.lock().unwrap(), [(0, 1), (0, 2), (1, 2)]);
+    }
+
+    #[test]
+    fn callback_removed_by_an_earlier_callback_is_not_called() {
+        let dispatcher = dispatcher();
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let weak = Arc::downgrade(&dispatcher);
+        let (counter, drops) = counted();
+        dispatcher.add(
+            CallbackId(0),
+            Box::new(move |_| {
+                weak.upgrade().unwrap().remove(CallbackId(1));
+            }),
+        );
+        let removed_calls = Arc::clone(&calls);
+        dispatcher.add(
+            CallbackId(1),
+            Box::new(move |value| {
+                let _ = &counter;
+                removed_calls.lock().unwrap().push(value);
+            }),
+        );
+
+        dispatcher.notify(1);
+        assert!(calls.lock().unwrap().is_empty());
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn callback_removing_itself_is_dropped_after_it_returned() {
+        let dispatcher = dispatcher();
+        let weak = Arc::downgrade(&dispatcher);
+        let (counter, drops) = counted();
+        let drops_during_call = Arc::new(AtomicUsize::new(usize::MAX));
+        let observed = Arc::clone(&drops_during_call);
+        let observed_drops = Arc::clone(&drops);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let later_calls = Arc::clone(&calls);
+        dispatcher.add(
+            CallbackId(0),
+            Box::new(move |_| {
+                let _ = &counter;
+                weak.upgrade().unwrap().remove(CallbackId(0));
+                observed.store(observed_drops.load(Ordering::SeqCst), Ordering::SeqCst);
+            }),
+        );
+        dispatcher.add(
+            CallbackId(1),
+            Box::new(move |_| {
+                later_calls.fetch_add(1, Ordering::SeqCst);
+            }),
+        );
+
+        dispatcher.notify(1);
+        assert_eq!(drops_during_call.load(Ordering::SeqCst), 0);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+        dispatcher.notify(2);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn log_policy_keeps_a_panicking_callback() {
+        let link = AblLink::new(120.);
+        link.set_callback_panic_policy(PanicPolicy::Log);
+        let calls = Arc::new(AtomicUsize::new(0));
+        let panicking_calls = Arc::clone(&calls);
+        let _subscription = link.set_tempo_callback(move |_| {
+            panicking_calls.fetch_add(1, Ordering::SeqCst);
+            panic!("test panic");
+        });
+
+        link.notify_tempo(120.);
+        link.notify_tempo(121.);
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn unregister_policy_removes_only_the_panicking_callback() {
+        let link = AblLink::new(120.);
+        link.set_callback_panic_policy(PanicPolicy::Unregister);
+        let (counter, drops) = counted();
+        let panicking_calls = Arc::new(AtomicUsize::new(0));
+        let other_calls = Arc::new(AtomicUsize::new(0));
+        let panicking = Arc::clone(&panicking_calls);
+        let other = Arc::clone(&other_calls);
+        let _panicking_subscription = link.set_start_stop_callback(move |_| {
+            let _ = &counter;
+            panicking.fetch_add(1, Ordering::SeqCst);
+            panic!("test panic");
+        });
+        let _other_subscription = link.set_start_stop_callback(move |_| {
+            other.fetch_add(1, Ordering::SeqCst);
+        });
+
+        link.notify_start_stop(true);
+        assert_eq!(drops.load(Ordering::SeqCst), 1);
+        link.notify_start_stop(false);
+        assert_eq!(panicking_calls.load(Ordering::SeqCst), 1);
+        assert_eq!(other_calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn abort_policy_aborts_the_process() {
+        if env::var_os(ABORT_CHILD_VAR).is_some() {
+            let link = AblLink::new(120.);
+            link.set_callback_panic_policy(PanicPolicy::Abort);
+            link.set_tempo_callback(|_| panic!("test panic")).detach();
+            link.notify_tempo(120.);
+            unreachable!("the dispatcher did not abort");
+        }
+
+        // Run this test again in a child process, which is expected to abort.
+        let status = Command::new(env::current_exe().unwrap())
+            .args([
+                "--exact",
+                "abl_link::tests::abort_policy_aborts_the_process",
+            ])
+            .env(ABORT_CHILD_VAR, "1")
+            .status()
+            .unwrap();
+        assert!(!status.success());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(status.signal(), Some(libc::SIGABRT));
+        }
     }
 }
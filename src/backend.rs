@@ -0,0 +1,133 @@
+use crate::{AblLink, Clock, SessionState};
+
+/// The functions of a Link session state, implemented by [SessionState] and by
+/// [SimulatedSessionState](crate::SimulatedSessionState).
+///
+/// Code which is generic over this trait and [LinkBackend] can be tested against a
+/// [SimulatedSession](crate::SimulatedSession) instead of a real Link session. See
+/// [SessionState] for the documentation of each function.
+pub trait SessionStateOps {
+    fn tempo(&self) -> f64;
+    fn set_tempo(&mut self, bpm: f64, at_time: i64);
+    fn beat_at_time(&self, time: i64, quantum: f64) -> f64;
+    fn phase_at_time(&self, time: i64, quantum: f64) -> f64;
+    fn time_at_beat(&self, beat: f64, quantum: f64) -> i64;
+    fn request_beat_at_time(&mut self, beat: f64, time: i64, quantum: f64);
+    fn force_beat_at_time(&mut self, beat: f64, time: i64, quantum: f64);
+    fn set_is_playing(&mut self, is_playing: bool, time: i64);
+    fn is_playing(&self) -> bool;
+    fn time_for_is_playing(&self) -> i64;
+    fn request_beat_at_start_playing_time(&mut self, beat: f64, quantum: f64);
+    fn set_is_playing_and_request_beat_at_time(
+        &mut self,
+        is_playing: bool,
+        time: i64,
+        beat: f64,
+        quantum: f64,
+    );
+}
+
+/// The app thread functions of a Link instance, implemented by [AblLink] and by
+/// [SimulatedLink](crate::SimulatedLink).
+///
+/// See [AblLink] for the documentation of each function. The time is read through the
+/// [Clock] supertrait.
+pub trait LinkBackend: Clock {
+    type SessionState: SessionStateOps + Default;
+
+    fn is_enabled(&self) -> bool;
+    fn enable(&self, enable: bool);
+    fn is_start_stop_sync_enabled(&self) -> bool;
+    fn enable_start_stop_sync(&self, enable: bool);
+    fn num_peers(&self) -> u64;
+    fn capture_app_session_state(&self, session_state: &mut Self::SessionState);
+    fn commit_app_session_state(&self, session_state: &Self::SessionState);
+}
+
+impl SessionStateOps for SessionState {
+    fn tempo(&self) -> f64 {
+        SessionState::tempo(self)
+    }
+
+    fn set_tempo(&mut self, bpm: f64, at_time: i64) {
+        SessionState::set_tempo(self, bpm, at_time)
+    }
+
+    fn beat_at_time(&self, time: i64, quantum: f64) -> f64 {
+        SessionState::beat_at_time(self, time, quantum)
+    }
+
+    fn phase_at_time(&self, time: i64, quantum: f64) -> f64 {
+        SessionState::phase_at_time(self, time, quantum)
+    }
+
+    fn time_at_beat(&self, beat: f64, quantum: f64) -> i64 {
+        SessionState::time_at_beat(self, beat, quantum)
+    }
+
+    fn request_beat_at_time(&mut self, beat: f64, time: i64, quantum: f64) {
+        SessionState::request_beat_at_time(self, beat, time, quantum)
+    }
+
+    fn force_beat_at_time(&mut self, beat: f64, time: i64, quantum: f64) {
+        SessionState::force_beat_at_time(self, beat, time, quantum)
+    }
+
+    fn set_is_playing(&mut self, is_playing: bool, time: i64) {
+        SessionState::set_is_playing(self, is_playing, time)
+    }
+
+    fn is_playing(&self) -> bool {
+        SessionState::is_playing(self)
+    }
+
+    fn time_for_is_playing(&self) -> i64 {
+        SessionState::time_for_is_playing(self)
+    }
+
+    fn request_beat_at_start_playing_time(&mut self, beat: f64, quantum: f64) {
+        SessionState::request_beat_at_start_playing_time(self, beat, quantum)
+    }
+
+    fn set_is_playing_and_request_beat_at_time(
+        &mut self,
+        is_playing: bool,
+        time: i64,
+        beat: f64,
+        quantum: f64,
+    ) {
+        SessionState::set_is_playing_and_request_beat_at_time(self, is_playing, time, beat, quantum)
+    }
+}
+
+impl LinkBackend for AblLink {
+    type SessionState = SessionState;
+
+    fn is_enabled(&self) -> bool {
+        AblLink::is_enabled(self)
+    }
+
+    fn enable(&self, enable: bool) {
+        AblLink::enable(self, enable)
+    }
+
+    fn is_start_stop_sync_enabled(&self) -> bool {
+        AblLink::is_start_stop_sync_enabled(self)
+    }
+
+    fn enable_start_stop_sync(&self, enable: bool) {
+        AblLink::enable_start_stop_sync(self, enable)
+    }
+
+    fn num_peers(&self) -> u64 {
+        AblLink::num_peers(self)
+    }
+
+    fn capture_app_session_state(&self, session_state: &mut SessionState) {
+        AblLink::capture_app_session_state(self, session_state)
+    }
+
+    fn commit_app_session_state(&self, session_state: &SessionState) {
+        AblLink::commit_app_session_state(self, session_state)
+    }
+}
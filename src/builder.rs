@@ -0,0 +1,172 @@
+use crate::{AblLink, Error};
+
+/// Builds an [AblLink] with an initial configuration.
+///
+/// All callbacks are registered and start/stop sync is configured before Link is enabled,
+/// so that no notification from peers joining right away is missed. The callbacks stay
+/// registered for the lifetime of the [AblLink], or until they are deleted with the
+/// `delete_..._callback` functions.
+///
+/// ```no_run
+/// use rusty_link::AblLinkBuilder;
+///
+/// let link = AblLinkBuilder::new()
+///     .tempo(128.)
+///     .start_stop_sync(true)
+///     .enabled(true)
+///     .tempo_callback(|tempo| println!("tempo: {tempo}"))
+///     .build()
+///     .expect("valid tempo");
+/// ```
+pub struct AblLinkBuilder {
+    tempo: f64,
+    enabled: bool,
+    start_stop_sync: bool,
+    num_peers_callback: Option<Box<dyn FnMut(u64) + Send>>,
+    tempo_callback: Option<Box<dyn FnMut(f64) + Send>>,
+    start_stop_callback: Option<Box<dyn FnMut(bool) + Send>>,
+}
+
+impl Default for AblLinkBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AblLinkBuilder {
+    /// A builder for a disabled [AblLink] with a tempo of 120 bpm, start/stop sync turned off
+    /// and no callbacks.
+    pub fn new() -> Self {
+        Self {
+            tempo: 120.,
+            enabled: false,
+            start_stop_sync: false,
+            num_peers_callback: None,
+            tempo_callback: None,
+            start_stop_callback: None,
+        }
+    }
+
+    /// The initial tempo in Beats Per Minute.
+    pub fn tempo(mut self, bpm: f64) -> Self {
+        self.tempo = bpm;
+        self
+    }
+
+    /// Enable Link after everything else has been set up.
+    pub fn enabled(mut self, enabled: bool) -> Self {
+        self.enabled = enabled;
+        self
+    }
+
+    /// Enable start/stop synchronization.
+    pub fn start_stop_sync(mut self, enabled: bool) -> Self {
+        self.start_stop_sync = enabled;
+        self
+    }
+
+    /// See [AblLink::set_num_peers_callback].
+    pub fn num_peers_callback<C: FnMut(u64) + Send + 'static>(mut self, closure: C) -> Self {
+        self.num_peers_callback = Some(Box::new(closure));
+        self
+    }
+
+    /// See [AblLink::set_tempo_callback].
+    pub fn tempo_callback<C: FnMut(f64) + Send + 'static>(mut self, closure: C) -> Self {
+        self.tempo_callback = Some(Box::new(closure));
+        self
+    }
+
+    /// See [AblLink::set_start_stop_callback].
+    pub fn start_stop_callback<C: FnMut(bool) + Send + 'static>(mut self, closure: C) -> Self {
+        self.start_stop_callback = Some(Box::new(closure));
+        self
+    }
+
+    /// Create the [AblLink] instance, register the callbacks and enable it, if requested.
+    ///
+    /// Returns [Error::InvalidTempo], if the tempo is not finite or outside of the range
+    /// Link supports, instead of letting Link clamp it. See [AblLink::try_new].
+    pub fn build(self) -> Result<AblLink, Error> {
+        let link = AblLink::try_new(self.tempo)?;
+
+        if let Some(callback) = self.num_peers_callback {
+            link.set_num_peers_callback(callback).detach();
+        }
+        if let Some(callback) = self.tempo_callback {
+            link.set_tempo_callback(callback).detach();
+        }
+        if let Some(callback) = self.start_stop_callback {
+            link.set_start_stop_callback(callback).detach();
+        }
+
+        link.enable_start_stop_sync(self.start_stop_sync);
+        link.enable(self.enabled);
+
+        Ok(link)
+    }
+}
+
+impl AblLink {
+    /// Start building an [AblLink] with an initial configuration. See [AblLinkBuilder].
+    pub fn builder() -> AblLinkBuilder {
+        AblLinkBuilder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn invalid_tempo_is_rejected() {
+        for bpm in [f64::NAN, f64::INFINITY, 19.9, 999.1] {
+            let result = AblLinkBuilder::new().tempo(bpm).build();
+            assert!(matches!(result, Err(Error::InvalidTempo(_))));
+        }
+    }
+
+    #[test]
+    fn start_stop_sync_is_applied() {
+        let link = AblLinkBuilder::new().start_stop_sync(true).build().unwrap();
+        assert!(link.is_start_stop_sync_enabled());
+        let link = AblLinkBuilder::new().build().unwrap();
+        assert!(!link.is_start_stop_sync_enabled());
+    }
+
+    #[test]
+    fn callbacks_are_registered_on_an_enabled_link() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let (num_peers_calls, tempo_calls, start_stop_calls) =
+            (Arc::clone(&calls), Arc::clone(&calls), Arc::clone(&calls));
+        let link = AblLinkBuilder::new()
+            .enabled(true)
+            .num_peers_callback(move |num_peers| {
+                num_peers_calls
+                    .lock()
+                    .unwrap()
+                    .push(format!("peers {num_peers}"))
+            })
+            .tempo_callback(move |tempo| tempo_calls.lock().unwrap().push(format!("tempo {tempo}")))
+            .start_stop_callback(move |is_playing| {
+                start_stop_calls
+                    .lock()
+                    .unwrap()
+                    .push(format!("playing {is_playing}"))
+            })
+            .build()
+            .unwrap();
+        assert!(link.is_enabled());
+
+        // Notifications of peers, which joined while Link was enabled, are received.
+        calls.lock().unwrap().clear();
+        link.notify_num_peers(1);
+        link.notify_tempo(130.);
+        link.notify_start_stop(true);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            ["peers 1", "tempo 130", "playing true"]
+        );
+    }
+}
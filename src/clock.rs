@@ -0,0 +1,180 @@
+use crate::{AblLink, AppHandle, AudioThreadHandle, Micros};
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicI64, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// A source of time in microseconds, like [AblLink::clock_micros].
+///
+/// The helpers of this crate, such as
+/// [HostTimeEstimator::sample_time_to_host_time_with](crate::HostTimeEstimator::sample_time_to_host_time_with),
+/// read the time from a Clock, so tests can step time with a [ManualClock] instead of
+/// sleeping. Implementations must be realtime-safe.
+pub trait Clock {
+    /// The current time in microseconds.
+    fn clock_micros(&self) -> i64;
+
+    /// The current time. Typed variant of [Clock::clock_micros].
+    fn clock(&self) -> Micros {
+        Micros::new(self.clock_micros())
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for &C {
+    fn clock_micros(&self) -> i64 {
+        (**self).clock_micros()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Arc<C> {
+    fn clock_micros(&self) -> i64 {
+        (**self).clock_micros()
+    }
+}
+
+impl Clock for AblLink {
+    fn clock_micros(&self) -> i64 {
+        AblLink::clock_micros(self)
+    }
+}
+
+impl Clock for AppHandle {
+    fn clock_micros(&self) -> i64 {
+        AblLink::clock_micros(self)
+    }
+}
+
+impl Clock for AudioThreadHandle {
+    fn clock_micros(&self) -> i64 {
+        AudioThreadHandle::clock_micros(self)
+    }
+}
+
+/// A [Clock] based on [Instant], which counts the microseconds since it was created.
+#[derive(Clone, Copy, Debug)]
+pub struct InstantClock {
+    start: Instant,
+}
+
+impl Default for InstantClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl InstantClock {
+    pub fn new() -> InstantClock {
+        InstantClock {
+            start: Instant::now(),
+        }
+    }
+}
+
+impl Clock for InstantClock {
+    fn clock_micros(&self) -> i64 {
+        self.start.elapsed().as_micros() as i64
+    }
+}
+
+/// A [Clock], which only moves when it is set or advanced by hand.
+///
+/// Clones share the same time, so one clone can be handed to the code under test, while
+/// the test steps the time with another.
+#[derive(Clone, Debug, Default)]
+pub struct ManualClock {
+    micros: Arc<AtomicI64>,
+}
+
+impl ManualClock {
+    /// Create a clock, which is at the given time in microseconds.
+    pub fn new(micros: i64) -> ManualClock {
+        ManualClock {
+            micros: Arc::new(AtomicI64::new(micros)),
+        }
+    }
+
+    /// Set the time in microseconds.
+    pub fn set(&self, micros: i64) {
+        self.micros.store(micros, Ordering::Release);
+    }
+
+    /// Move the time forward. Panics, if the time overflows, like [Micros] does.
+    pub fn advance(&self, duration: Duration) {
+        let micros = Micros::from_duration(duration).expect("duration out of range");
+        self.micros
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |now| {
+                now.checked_add(micros.get())
+            })
+            .expect("overflow when advancing ManualClock");
+    }
+}
+
+impl Clock for ManualClock {
+    fn clock_micros(&self) -> i64 {
+        self.micros.load(Ordering::Acquire)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn manual_clock_is_set_and_advanced() {
+        let clock = ManualClock::new(-5);
+        assert_eq!(clock.clock_micros(), -5);
+        clock.advance(Duration::from_millis(2));
+        assert_eq!(clock.clock_micros(), 1_995);
+        // Sub-microsecond parts are truncated.
+        clock.advance(Duration::from_nanos(1_999));
+        assert_eq!(clock.clock(), Micros::new(1_996));
+        // Setting the time may move it backwards.
+        clock.set(42);
+        assert_eq!(clock.clock_micros(), 42);
+    }
+
+    #[test]
+    fn manual_clock_clones_share_the_time() {
+        let clock = ManualClock::default();
+        let shared = Arc::new(clock.clone());
+        let reference = &clock;
+        clock.advance(Duration::from_secs(1));
+        assert_eq!(shared.clock_micros(), 1_000_000);
+        assert_eq!(reference.clock_micros(), 1_000_000);
+
+        let handle = thread::spawn(move || shared.set(7));
+        handle.join().unwrap();
+        assert_eq!(clock.clock_micros(), 7);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when advancing ManualClock")]
+    fn manual_clock_panics_on_overflow() {
+        let clock = ManualClock::new(i64::MAX);
+        clock.advance(Duration::from_micros(1));
+    }
+
+    #[test]
+    fn instant_clock_is_monotonic() {
+        let clock = InstantClock::new();
+        let start = clock.clock_micros();
+        assert!(start >= 0);
+        let mut last = start;
+        for _ in 0..10_000 {
+            let now = clock.clock_micros();
+            assert!(now >= last, "{now} < {last}");
+            last = now;
+        }
+
+        thread::sleep(Duration::from_millis(2));
+        assert!(clock.clock_micros() >= last + 2_000);
+        // Copies count from the same start.
+        let copy = clock;
+        let before = clock.clock_micros();
+        assert!(copy.clock_micros() >= before);
+    }
+}
@@ -0,0 +1,360 @@
+use crate::Clock;
+use std::time::{Duration, Instant, SystemTime};
+
+// Number of clock readings per calibration. The reading with the shortest round trip on
+// the Link clock is used.
+const CALIBRATION_SAMPLES: usize = 16;
+
+/// A `struct timespec` of `CLOCK_MONOTONIC`, as used by ALSA and other POSIX APIs.
+#[cfg(unix)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timespec {
+    pub tv_sec: i64,
+    pub tv_nsec: i64,
+}
+
+#[cfg(unix)]
+impl Timespec {
+    /// The current time of `CLOCK_MONOTONIC`.
+    pub fn monotonic_now() -> Timespec {
+        let mut timespec = libc::timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        };
+        // Can not fail with a valid clock id and pointer.
+        unsafe { libc::clock_gettime(libc::CLOCK_MONOTONIC, &mut timespec) };
+        timespec.into()
+    }
+
+    fn as_nanos(&self) -> i128 {
+        self.tv_sec as i128 * 1_000_000_000 + self.tv_nsec as i128
+    }
+
+    fn from_nanos(nanos: i128) -> Timespec {
+        Timespec {
+            tv_sec: nanos.div_euclid(1_000_000_000) as i64,
+            tv_nsec: nanos.rem_euclid(1_000_000_000) as i64,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<libc::timespec> for Timespec {
+    #[allow(clippy::unnecessary_cast)]
+    fn from(timespec: libc::timespec) -> Self {
+        Timespec {
+            tv_sec: timespec.tv_sec as i64,
+            tv_nsec: timespec.tv_nsec as i64,
+        }
+    }
+}
+
+#[cfg(unix)]
+impl From<Timespec> for libc::timespec {
+    fn from(timespec: Timespec) -> Self {
+        libc::timespec {
+            tv_sec: timespec.tv_sec as libc::time_t,
+            tv_nsec: timespec.tv_nsec as libc::c_long,
+        }
+    }
+}
+
+/// Readings of all clocks at (almost) the same time.
+#[derive(Clone, Copy, Debug)]
+struct Calibration {
+    link_micros: i64,
+    instant: Instant,
+    system_time: SystemTime,
+    #[cfg(unix)]
+    monotonic_nanos: i128,
+    // Half of the round trip on the Link clock around the readings of the other clocks,
+    // plus the resolution of the Link clock.
+    uncertainty_micros: i64,
+}
+
+impl Calibration {
+    fn measure(clock: &impl Clock) -> Calibration {
+        (0..CALIBRATION_SAMPLES)
+            .map(|_| {
+                let before = clock.clock_micros();
+                let instant = Instant::now();
+                let system_time = SystemTime::now();
+                #[cfg(unix)]
+                let monotonic_nanos = Timespec::monotonic_now().as_nanos();
+                let after = clock.clock_micros();
+                Calibration {
+                    link_micros: before + (after - before) / 2,
+                    instant,
+                    system_time,
+                    #[cfg(unix)]
+                    monotonic_nanos,
+                    uncertainty_micros: (after - before) / 2 + 1,
+                }
+            })
+            .min_by_key(|calibration| calibration.uncertainty_micros)
+            .expect("at least one calibration sample")
+    }
+}
+
+// The signed difference `a - b` in microseconds.
+fn micros_between(a: Instant, b: Instant) -> i64 {
+    match a.checked_duration_since(b) {
+        Some(duration) => duration.as_micros() as i64,
+        None => -((b - a).as_micros() as i64),
+    }
+}
+
+fn system_micros_between(a: SystemTime, b: SystemTime) -> i64 {
+    match a.duration_since(b) {
+        Ok(duration) => duration.as_micros() as i64,
+        Err(error) => -(error.duration().as_micros() as i64),
+    }
+}
+
+/// Converts between the Link clock and [Instant], [SystemTime] and, on Unix,
+/// `CLOCK_MONOTONIC` [Timespec]s.
+///
+/// The mapping reads all clocks at (almost) the same time, and keeps the reading with the
+/// shortest round trip on the Link clock. Because clocks drift apart and [SystemTime]
+/// can be adjusted, [ClockMapping::update] should be called periodically, for example
+/// from a UI or control thread. It re-estimates the mapping, once the recalibration
+/// interval has passed.
+///
+/// All conversions are realtime-safe.
+#[derive(Clone, Copy, Debug)]
+pub struct ClockMapping<C: Clock> {
+    clock: C,
+    recalibration_interval: Duration,
+    calibration: Calibration,
+}
+
+impl<C: Clock> ClockMapping<C> {
+    /// Create a mapping between the given Link clock, for example an
+    /// [AblLink](crate::AblLink), and the system clocks, which is recalibrated every second.
+    pub fn new(clock: C) -> ClockMapping<C> {
+        Self::with_interval(clock, Duration::from_secs(1))
+    }
+
+    /// Create a mapping, which is recalibrated by [ClockMapping::update] once the
+    /// given interval has passed.
+    pub fn with_interval(clock: C, recalibration_interval: Duration) -> ClockMapping<C> {
+        let calibration = Calibration::measure(&clock);
+        ClockMapping {
+            clock,
+            recalibration_interval,
+            calibration,
+        }
+    }
+
+    /// Re-estimate the mapping now.
+    pub fn calibrate(&mut self) {
+        self.calibration = Calibration::measure(&self.clock);
+    }
+
+    /// Re-estimate the mapping, if the recalibration interval has passed since the last
+    /// calibration. Returns true, if the mapping was re-estimated.
+    pub fn update(&mut self) -> bool {
+        let due = self.calibration.instant.elapsed() >= self.recalibration_interval;
+        if due {
+            self.calibrate();
+        }
+        due
+    }
+
+    /// How far off the conversions can be because of the calibration, in microseconds.
+    /// This does not include the drift of the clocks since the last calibration.
+    pub fn uncertainty_micros(&self) -> i64 {
+        self.calibration.uncertainty_micros
+    }
+
+    /// The time since the last calibration.
+    pub fn calibration_age(&self) -> Duration {
+        self.calibration.instant.elapsed()
+    }
+
+    /// Convert an [Instant] to Link clock microseconds.
+    pub fn instant_to_micros(&self, instant: Instant) -> i64 {
+        self.calibration.link_micros + micros_between(instant, self.calibration.instant)
+    }
+
+    /// Convert Link clock microseconds to an [Instant]. Returns `None`, if the Instant
+    /// can not represent the time.
+    pub fn micros_to_instant(&self, micros: i64) -> Option<Instant> {
+        let offset = micros.checked_sub(self.calibration.link_micros)?;
+        let duration = Duration::from_micros(offset.unsigned_abs());
+        if offset >= 0 {
+            self.calibration.instant.checked_add(duration)
+        } else {
+            self.calibration.instant.checked_sub(duration)
+        }
+    }
+
+    /// Convert a [SystemTime] to Link clock microseconds.
+    pub fn system_time_to_micros(&self, system_time: SystemTime) -> i64 {
+        self.calibration.link_micros
+            + system_micros_between(system_time, self.calibration.system_time)
+    }
+
+    /// Convert Link clock microseconds to a [SystemTime]. Returns `None`, if the
+    /// SystemTime can not represent the time.
+    pub fn micros_to_system_time(&self, micros: i64) -> Option<SystemTime> {
+        let offset = micros.checked_sub(self.calibration.link_micros)?;
+        let duration = Duration::from_micros(offset.unsigned_abs());
+        if offset >= 0 {
+            self.calibration.system_time.checked_add(duration)
+        } else {
+            self.calibration.system_time.checked_sub(duration)
+        }
+    }
+
+    /// Convert a `CLOCK_MONOTONIC` [Timespec] to Link clock microseconds.
+    #[cfg(unix)]
+    pub fn timespec_to_micros(&self, timespec: Timespec) -> i64 {
+        let nanos = timespec.as_nanos() - self.calibration.monotonic_nanos;
+        self.calibration.link_micros + (nanos as f64 / 1e3).round() as i64
+    }
+
+    /// Convert Link clock microseconds to a `CLOCK_MONOTONIC` [Timespec].
+    #[cfg(unix)]
+    pub fn micros_to_timespec(&self, micros: i64) -> Timespec {
+        let offset = (micros as i128 - self.calibration.link_micros as i128) * 1_000;
+        Timespec::from_nanos(self.calibration.monotonic_nanos + offset)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{InstantClock, ManualClock};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    // A clock, which moves forward by the next of the given steps on every reading.
+    struct SteppingClock {
+        steps: Vec<i64>,
+        readings: AtomicUsize,
+    }
+
+    impl Clock for SteppingClock {
+        fn clock_micros(&self) -> i64 {
+            let readings = self.readings.fetch_add(1, Ordering::Relaxed) + 1;
+            (0..readings)
+                .map(|i| self.steps[i % self.steps.len()])
+                .sum()
+        }
+    }
+
+    fn assert_within(micros: i64, before: i64, after: i64, uncertainty: i64) {
+        // One more microsecond for truncating the clocks to microseconds.
+        let slack = uncertainty + 1;
+        assert!(
+            (before - slack..=after + slack).contains(&micros),
+            "{micros} not in {before}..={after} +- {slack}"
+        );
+    }
+
+    #[test]
+    fn uncertainty_is_half_the_shortest_round_trip() {
+        let steps = |steps: Vec<i64>| SteppingClock {
+            steps,
+            readings: AtomicUsize::new(0),
+        };
+        assert_eq!(
+            ClockMapping::new(ManualClock::new(5)).uncertainty_micros(),
+            1
+        );
+        assert_eq!(ClockMapping::new(steps(vec![10])).uncertainty_micros(), 6);
+        assert_eq!(
+            ClockMapping::new(steps(vec![1000])).uncertainty_micros(),
+            501
+        );
+        assert_eq!(
+            ClockMapping::new(steps(vec![100, 4, 50])).uncertainty_micros(),
+            3
+        );
+    }
+
+    #[test]
+    fn instant_round_trip() {
+        let mapping = ClockMapping::new(ManualClock::new(1_000_000));
+        for micros in [-5_000_000_000, 0, 999_999, 1_000_000, 123_456_789_000] {
+            let instant = mapping.micros_to_instant(micros).unwrap();
+            assert_eq!(mapping.instant_to_micros(instant), micros);
+        }
+        assert_eq!(mapping.micros_to_instant(i64::MIN), None);
+    }
+
+    #[test]
+    fn instant_matches_the_link_clock() {
+        let clock = InstantClock::new();
+        let mapping = ClockMapping::new(clock);
+        thread_sleep();
+        let before = clock.clock_micros();
+        let micros = mapping.instant_to_micros(Instant::now());
+        let after = clock.clock_micros();
+        assert_within(micros, before, after, mapping.uncertainty_micros());
+    }
+
+    #[test]
+    fn system_time_round_trip() {
+        let mapping = ClockMapping::new(ManualClock::new(1_000_000));
+        for micros in [-5_000_000_000, 0, 1_000_000, 123_456_789_000] {
+            let system_time = mapping.micros_to_system_time(micros).unwrap();
+            assert_eq!(mapping.system_time_to_micros(system_time), micros);
+        }
+        assert_eq!(mapping.micros_to_system_time(i64::MIN), None);
+
+        let clock = InstantClock::new();
+        let mapping = ClockMapping::new(clock);
+        thread_sleep();
+        let before = clock.clock_micros();
+        let micros = mapping.system_time_to_micros(SystemTime::now());
+        let after = clock.clock_micros();
+        assert_within(micros, before, after, mapping.uncertainty_micros());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timespec_round_trip() {
+        let mapping = ClockMapping::new(ManualClock::new(1_000_000));
+        for micros in [i64::MIN, -5_000_000_000, 0, 1_000_000, 123_456_789_000] {
+            let timespec = mapping.micros_to_timespec(micros);
+            assert!((0..1_000_000_000).contains(&timespec.tv_nsec));
+            if micros != i64::MIN {
+                assert_eq!(mapping.timespec_to_micros(timespec), micros);
+            }
+            let libc_timespec: libc::timespec = timespec.into();
+            assert_eq!(Timespec::from(libc_timespec), timespec);
+        }
+
+        let clock = InstantClock::new();
+        let mapping = ClockMapping::new(clock);
+        thread_sleep();
+        let before = clock.clock_micros();
+        let micros = mapping.timespec_to_micros(Timespec::monotonic_now());
+        let after = clock.clock_micros();
+        assert_within(micros, before, after, mapping.uncertainty_micros());
+    }
+
+    #[test]
+    fn update_recalibrates_after_the_interval() {
+        let clock = ManualClock::new(0);
+        let hour = Duration::from_secs(3600);
+        let mut rarely = ClockMapping::with_interval(clock.clone(), hour);
+        let mut always = ClockMapping::with_interval(clock.clone(), Duration::ZERO);
+        let rarely_calibrated_at = rarely.micros_to_instant(0).unwrap();
+        let always_calibrated_at = always.micros_to_instant(0).unwrap();
+
+        // Let the Link clock jump much further than the real time moves during the test.
+        clock.set(hour.as_micros() as i64);
+        assert!(!rarely.update());
+        assert_eq!(rarely.instant_to_micros(rarely_calibrated_at), 0);
+        assert!(always.update());
+        assert!(always.instant_to_micros(always_calibrated_at) > hour.as_micros() as i64 / 2);
+        assert!(always.calibration_age() < hour);
+    }
+
+    // Moves the readings away from the calibration.
+    fn thread_sleep() {
+        std::thread::sleep(Duration::from_millis(5));
+    }
+}
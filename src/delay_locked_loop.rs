@@ -0,0 +1,108 @@
+use crate::HostTimeEstimator;
+use std::f64::consts::{SQRT_2, TAU};
+
+const DEFAULT_BANDWIDTH: f64 = 0.5;
+
+/// A second-order delay-locked loop, which estimates the host time of the sample clock,
+/// as used by JACK and PipeWire. See Fons Adriaensen, "Using a DLL to filter time".
+///
+/// The loop predicts the host time of each callback from the previous one and the
+/// filtered duration of a sample, and corrects both by a part of the prediction error.
+/// Compared to the linear regression of the [HostTimeFilter](crate::HostTimeFilter), it
+/// follows sudden jumps of the host time smoothly within about `1 / bandwidth` seconds,
+/// instead of being pulled off for the whole window.
+#[derive(Clone, Copy, Debug)]
+pub struct DelayLockedLoop {
+    sample_rate: f64,
+    bandwidth: f64,
+    // State after the last callback, or None before the first one
+    state: Option<LoopState>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct LoopState {
+    sample_clock: u64,
+    // Filtered host time of `sample_clock` in micros
+    time: f64,
+    // Filtered duration of one sample in micros
+    period: f64,
+}
+
+impl DelayLockedLoop {
+    /// Create a loop for the given nominal sample rate in Hz, with a bandwidth of 0.5 Hz.
+    pub fn new(sample_rate: f64) -> Self {
+        Self::with_bandwidth(sample_rate, DEFAULT_BANDWIDTH)
+    }
+
+    /// Create a loop for the given nominal sample rate and bandwidth in Hz. A lower
+    /// bandwidth filters more jitter, but takes longer to follow changes.
+    ///
+    /// Panics, if the sample rate or bandwidth are not finite and positive.
+    pub fn with_bandwidth(sample_rate: f64, bandwidth: f64) -> Self {
+        assert!(
+            sample_rate.is_finite() && sample_rate > 0.,
+            "Sample rate must be finite and positive."
+        );
+        assert!(
+            bandwidth.is_finite() && bandwidth > 0.,
+            "Bandwidth must be finite and positive."
+        );
+        Self {
+            sample_rate,
+            bandwidth,
+            state: None,
+        }
+    }
+
+    /// Forget the state of the loop. The next callback starts it again.
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+
+    /// The filtered host time in microseconds of the given sample clock.
+    pub fn sample_time_to_host_time(&mut self, clock_micros: i64, sample_clock: u64) -> i64 {
+        let state = match self.state {
+            // A sample clock that went backwards means the device was restarted
+            Some(state) if sample_clock >= state.sample_clock => state,
+            _ => {
+                self.state = Some(LoopState {
+                    sample_clock,
+                    time: clock_micros as f64,
+                    period: 1e6 / self.sample_rate,
+                });
+                return clock_micros;
+            }
+        };
+        if sample_clock == state.sample_clock {
+            return state.time.round() as i64;
+        }
+
+        let samples = (sample_clock - state.sample_clock) as f64;
+        let predicted = state.time + state.period * samples;
+        let error = clock_micros as f64 - predicted;
+
+        // The loop coefficients for the time since the last callback. Limited, so the loop
+        // stays stable after long gaps.
+        let omega = (TAU * self.bandwidth * samples / self.sample_rate).min(1.);
+        let b = SQRT_2 * omega;
+        let c = omega * omega;
+
+        let time = predicted + b * error;
+        self.state = Some(LoopState {
+            sample_clock,
+            time,
+            period: state.period + c * error / samples,
+        });
+        time.round() as i64
+    }
+}
+
+impl HostTimeEstimator for DelayLockedLoop {
+    fn sample_time_to_host_time(&mut self, clock_micros: i64, sample_clock: u64) -> i64 {
+        DelayLockedLoop::sample_time_to_host_time(self, clock_micros, sample_clock)
+    }
+
+    fn reset(&mut self) {
+        DelayLockedLoop::reset(self)
+    }
+}
@@ -0,0 +1,75 @@
+use std::fmt;
+
+/// The lowest tempo in Beats Per Minute, which Link supports.
+pub const MIN_TEMPO: f64 = 20.0;
+
+/// The highest tempo in Beats Per Minute, which Link supports.
+pub const MAX_TEMPO: f64 = 999.0;
+
+/// The error type of rusty_link.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Error {
+    /// abl_link failed to create an instance, for example because allocating it failed.
+    LinkCreationFailed,
+    /// abl_link failed to create a session state, for example because allocating it failed.
+    SessionStateCreationFailed,
+    /// The tempo is not finite, or outside of the range from [MIN_TEMPO] to [MAX_TEMPO].
+    InvalidTempo(f64),
+    /// The quantum is not finite, or negative.
+    InvalidQuantum(f64),
+    /// The beat is not finite.
+    InvalidBeat(f64),
+    /// The time does not fit into an i64 of microseconds, or a negative time was
+    /// converted to a [Duration](std::time::Duration).
+    TimeOutOfRange,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::LinkCreationFailed => write!(f, "failed to create an abl_link instance"),
+            Error::SessionStateCreationFailed => {
+                write!(f, "failed to create an abl_link session state")
+            }
+            Error::InvalidTempo(bpm) => write!(
+                f,
+                "invalid tempo {bpm}, expected a value from {MIN_TEMPO} to {MAX_TEMPO} bpm"
+            ),
+            Error::InvalidQuantum(quantum) => write!(
+                f,
+                "invalid quantum {quantum}, expected a finite value of at least 0"
+            ),
+            Error::InvalidBeat(beat) => write!(f, "invalid beat {beat}, expected a finite value"),
+            Error::TimeOutOfRange => write!(f, "time out of range"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Returns the tempo, if it is finite and within the range Link supports.
+pub(crate) fn validate_tempo(bpm: f64) -> Result<f64, Error> {
+    if bpm.is_finite() && (MIN_TEMPO..=MAX_TEMPO).contains(&bpm) {
+        Ok(bpm)
+    } else {
+        Err(Error::InvalidTempo(bpm))
+    }
+}
+
+/// Returns the quantum, if it is finite and not negative.
+pub(crate) fn validate_quantum(quantum: f64) -> Result<f64, Error> {
+    if quantum.is_finite() && quantum >= 0. {
+        Ok(quantum)
+    } else {
+        Err(Error::InvalidQuantum(quantum))
+    }
+}
+
+/// Returns the beat, if it is finite.
+pub(crate) fn validate_beat(beat: f64) -> Result<f64, Error> {
+    if beat.is_finite() {
+        Ok(beat)
+    } else {
+        Err(Error::InvalidBeat(beat))
+    }
+}
@@ -0,0 +1,234 @@
+use crate::{AblLink, EventsConfig, LinkEvent, LinkEvents, SessionState};
+use futures_core::Stream;
+use std::{
+    future,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+/// An async [Stream] of [LinkEvent]s, created by [AblLink::event_stream].
+///
+/// Events which occur before the stream is polled for the first time are kept
+/// and returned by the first poll. The stream ends once the [AblLink] is dropped.
+pub struct LinkEventStream {
+    events: LinkEvents,
+}
+
+impl LinkEventStream {
+    async fn next(&mut self) -> Option<LinkEvent> {
+        future::poll_fn(|cx| self.events.poll_recv(cx)).await
+    }
+}
+
+impl Stream for LinkEventStream {
+    type Item = LinkEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<LinkEvent>> {
+        self.events.poll_recv(cx)
+    }
+}
+
+impl AblLink {
+    ///  Receive peer, tempo and start/stop notifications as an async [Stream] of
+    ///  [LinkEvent]s, backed by an unbounded and lossless channel.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: no
+    pub fn event_stream(&self) -> LinkEventStream {
+        self.event_stream_with(EventsConfig::default())
+    }
+
+    ///  Receive peer, tempo and start/stop notifications as an async [Stream] of
+    ///  [LinkEvent]s, backed by a channel with the given capacity and delivery.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: no
+    ///
+    ///  Note that with [Delivery::Backpressure](crate::Delivery::Backpressure), a full
+    ///  channel blocks the Link-managed thread until the stream is polled again, for at
+    ///  most the timeout.
+    pub fn event_stream_with(&self, config: EventsConfig) -> LinkEventStream {
+        LinkEventStream {
+            events: self.events_with(config),
+        }
+    }
+
+    ///  Wait until at least `num_peers` peers are connected in the Link session.
+    ///  Returns the number of connected peers, or `None` if the notifications ended,
+    ///  because the callbacks were deleted with the `delete_..._callback` functions.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: no
+    pub async fn wait_for_peers(&self, num_peers: u64) -> Option<u64> {
+        // Subscribe first, so that no change is missed between the check and the wait.
+        let mut stream = self.event_stream();
+        let current = self.num_peers();
+        if current >= num_peers {
+            return Some(current);
+        }
+        while let Some(event) = stream.next().await {
+            if let LinkEvent::PeersChanged(current) = event
+                && current >= num_peers
+            {
+                return Some(current);
+            }
+        }
+        None
+    }
+
+    ///  Wait until the transport of the Link session is playing. Returns false, if the
+    ///  notifications ended, because the callbacks were deleted with the
+    ///  `delete_..._callback` functions.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: no
+    pub async fn wait_until_playing(&self) -> bool {
+        let mut stream = self.event_stream();
+        let mut session_state = SessionState::new();
+        self.capture_app_session_state(&mut session_state);
+        if session_state.is_playing() {
+            return true;
+        }
+        while let Some(event) = stream.next().await {
+            if event == LinkEvent::StartStop(true) {
+                return true;
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::{
+            Arc,
+            atomic::{AtomicBool, AtomicUsize, Ordering},
+        },
+        task::Wake,
+        thread::{self, Thread},
+        time::Duration,
+    };
+
+    // Counts how often it was woken.
+    #[derive(Default)]
+    struct CountingWaker(AtomicUsize);
+
+    impl Wake for CountingWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn poll_next(
+        stream: &mut LinkEventStream,
+        waker: &Arc<CountingWaker>,
+    ) -> Poll<Option<LinkEvent>> {
+        let waker = Arc::clone(waker).into();
+        Pin::new(stream).poll_next(&mut Context::from_waker(&waker))
+    }
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    // Poll the future on this thread until it is ready.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+            thread::park();
+        }
+    }
+
+    #[test]
+    fn first_poll_returns_an_earlier_event() {
+        let link = AblLink::new(120.);
+        let mut stream = link.event_stream();
+        link.notify_tempo(130.);
+
+        let waker = Arc::new(CountingWaker::default());
+        assert_eq!(
+            poll_next(&mut stream, &waker),
+            Poll::Ready(Some(LinkEvent::TempoChanged(130.)))
+        );
+    }
+
+    #[test]
+    fn pending_poll_is_woken_by_the_next_event() {
+        let link = AblLink::new(120.);
+        let mut stream = link.event_stream();
+        let waker = Arc::new(CountingWaker::default());
+        assert_eq!(poll_next(&mut stream, &waker), Poll::Pending);
+        assert_eq!(waker.0.load(Ordering::SeqCst), 0);
+
+        link.notify_num_peers(2);
+        assert_eq!(waker.0.load(Ordering::SeqCst), 1);
+        assert_eq!(
+            poll_next(&mut stream, &waker),
+            Poll::Ready(Some(LinkEvent::PeersChanged(2)))
+        );
+    }
+
+    #[test]
+    fn stream_ends_when_the_link_is_dropped() {
+        let link = AblLink::new(120.);
+        let mut stream = link.event_stream();
+        let waker = Arc::new(CountingWaker::default());
+        assert_eq!(poll_next(&mut stream, &waker), Poll::Pending);
+
+        drop(link);
+        assert!(waker.0.load(Ordering::SeqCst) > 0);
+        assert_eq!(poll_next(&mut stream, &waker), Poll::Ready(None));
+    }
+
+    #[test]
+    fn wait_for_peers_resolves_once_enough_peers_joined() {
+        let link = AblLink::new(120.);
+        let done = AtomicBool::new(false);
+        let num_peers = thread::scope(|scope| {
+            // Keep notifying, as the notifications before the wait started are not seen.
+            scope.spawn(|| {
+                while !done.load(Ordering::SeqCst) {
+                    link.notify_num_peers(1);
+                    link.notify_num_peers(2);
+                    thread::sleep(Duration::from_millis(5));
+                }
+            });
+            let num_peers = block_on(link.wait_for_peers(2));
+            done.store(true, Ordering::SeqCst);
+            num_peers
+        });
+        assert_eq!(num_peers, Some(2));
+    }
+
+    #[test]
+    fn wait_until_playing_resolves_after_a_start_stop_commit() {
+        let link = AblLink::new(120.);
+        let is_playing = thread::scope(|scope| {
+            // Playing is checked before the wait, so the commit can happen at any time.
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(20));
+                let mut session_state = SessionState::new();
+                link.capture_app_session_state(&mut session_state);
+                session_state.set_is_playing(true, link.clock_micros());
+                link.commit_app_session_state(&session_state);
+            });
+            block_on(link.wait_until_playing())
+        });
+        assert!(is_playing);
+    }
+}
@@ -0,0 +1,465 @@
+#[cfg(feature = "rt-check")]
+use crate::rt_check;
+use crate::{AblLink, Subscription};
+use crossbeam_channel::{
+    Receiver, RecvError, RecvTimeoutError, Sender, TryRecvError, bounded, select, unbounded,
+};
+use std::{
+    mem,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    task::Waker,
+    time::Duration,
+};
+
+/// A notification from a Link session, as delivered by [AblLink::events].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LinkEvent {
+    /// The number of peers in the Link session changed.
+    PeersChanged(u64),
+    /// The session tempo changed, in Beats Per Minute.
+    TempoChanged(f64),
+    /// The start/stop isPlaying state changed.
+    StartStop(bool),
+}
+
+impl LinkEvent {
+    fn is_same_kind(&self, other: &LinkEvent) -> bool {
+        mem::discriminant(self) == mem::discriminant(other)
+    }
+}
+
+/// How many events can be pending in a [LinkEvents] channel, before [Delivery] decides
+/// what happens with the next event.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Capacity {
+    #[default]
+    Unbounded,
+    /// Holds at most this many events (at least 1).
+    Bounded(usize),
+}
+
+/// What happens with an event, which can not be received right away.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Delivery {
+    /// Every event is delivered and the Link-managed thread never waits for the receiver.
+    /// The channel grows for as long as the receiver falls behind, so this needs
+    /// [Capacity::Unbounded].
+    #[default]
+    Lossless,
+    /// A new event replaces a pending event of the same kind, so that only the latest
+    /// value of each kind is received. If there is none and a bounded channel is full,
+    /// the oldest pending event is dropped. Never blocks the Link-managed thread.
+    Coalescing,
+    /// Every event is delivered, as long as the receiver keeps up. If a bounded channel
+    /// is full, the Link-managed thread waits for room for at most `timeout`, which also
+    /// delays all other callbacks. After that the receiver counts as stalled, and events
+    /// are coalesced like with [Delivery::Coalescing] without waiting, until the receiver
+    /// made room again.
+    Backpressure { timeout: Duration },
+}
+
+/// Configuration of the channel returned by [AblLink::events_with].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct EventsConfig {
+    pub capacity: Capacity,
+    pub delivery: Delivery,
+}
+
+/// The state shared by the senders and the [LinkEvents].
+struct Shared {
+    delivery: Delivery,
+    // Takes the pending events out of the channel to coalesce them with a new one. The
+    // lock makes the senders coalesce one at a time.
+    pending: Mutex<Receiver<LinkEvent>>,
+    // Disconnected once the LinkEvents are dropped.
+    closed: Receiver<()>,
+    // The receiver did not make room in a full channel in time.
+    stalled: AtomicBool,
+    // Task of an async receiver, which is waiting for the next event.
+    waker: Mutex<Option<Waker>>,
+}
+
+impl Shared {
+    fn wake(&self) {
+        if let Some(waker) = self.waker.lock().unwrap().take() {
+            waker.wake();
+        }
+    }
+}
+
+/// Wakes an async receiver when dropped.
+struct WakeOnDrop(Arc<Shared>);
+
+impl Clone for WakeOnDrop {
+    fn clone(&self) -> Self {
+        Self(Arc::clone(&self.0))
+    }
+}
+
+impl Drop for WakeOnDrop {
+    fn drop(&mut self) {
+        self.0.wake();
+    }
+}
+
+/// The sending half of a [LinkEvents] channel, owned by the registered callbacks.
+#[derive(Clone)]
+struct EventSender {
+    sender: Sender<LinkEvent>,
+    // Dropped after the sender, so that a woken receiver can tell that the channel was
+    // disconnected.
+    shared: WakeOnDrop,
+}
+
+impl EventSender {
+    fn send(&self, event: LinkEvent) {
+        let shared = &self.shared.0;
+        match shared.delivery {
+            // Unbounded, so this never waits.
+            Delivery::Lossless => drop(self.sender.send(event)),
+            Delivery::Coalescing => self.coalesce(event),
+            Delivery::Backpressure { timeout } => {
+                if shared.stalled.load(Ordering::Relaxed) && self.sender.is_full() {
+                    self.coalesce(event);
+                } else {
+                    shared.stalled.store(false, Ordering::Relaxed);
+                    select! {
+                        send(self.sender, event) -> _ => {}
+                        recv(shared.closed) -> _ => {}
+                        default(timeout) => {
+                            shared.stalled.store(true, Ordering::Relaxed);
+                            self.coalesce(event);
+                        }
+                    }
+                }
+            }
+        }
+        shared.wake();
+    }
+
+    // Replace a pending event of the same kind, or drop the oldest one to make room.
+    fn coalesce(&self, event: LinkEvent) {
+        let pending = self.shared.0.pending.lock().unwrap();
+        let mut events: Vec<LinkEvent> = pending.try_iter().collect();
+        match events.iter_mut().find(|e| e.is_same_kind(&event)) {
+            Some(pending) => *pending = event,
+            None => events.push(event),
+        }
+        if let Some(capacity) = self.sender.capacity() {
+            events.drain(..events.len().saturating_sub(capacity));
+        }
+        for event in events {
+            let _ = self.sender.try_send(event);
+        }
+    }
+}
+
+/// The receiving half of a channel of [LinkEvent]s, created by [AblLink::events].
+///
+/// The callbacks feeding the channel stay registered for as long as the receiver exists.
+/// Once the [AblLink] is dropped and all pending events were received, the receive
+/// functions return a disconnected error.
+pub struct LinkEvents {
+    receiver: Receiver<LinkEvent>,
+    #[cfg(feature = "async")]
+    shared: Arc<Shared>,
+    // Dropped before the subscriptions, to wake a Link-managed thread, which is waiting
+    // for room in a full channel.
+    _closed: Sender<()>,
+    _subscriptions: [Subscription; 3],
+}
+
+impl LinkEvents {
+    /// Block until the next event is available.
+    pub fn recv(&self) -> Result<LinkEvent, RecvError> {
+        self.receiver.recv()
+    }
+
+    /// Return the next event, if one is available without blocking.
+    pub fn try_recv(&self) -> Result<LinkEvent, TryRecvError> {
+        self.receiver.try_recv()
+    }
+
+    /// Block until the next event is available, or the timeout has elapsed.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<LinkEvent, RecvTimeoutError> {
+        self.receiver.recv_timeout(timeout)
+    }
+
+    /// An iterator over all events, which are available without blocking.
+    pub fn try_iter(&self) -> impl Iterator<Item = LinkEvent> + '_ {
+        self.receiver.try_iter()
+    }
+
+    /// The receiver of the underlying channel, for example to wait for Link events and
+    /// other channels at once with [crossbeam_channel::select].
+    pub fn receiver(&self) -> &Receiver<LinkEvent> {
+        &self.receiver
+    }
+
+    /// Poll for the next event from an async task. Returns `None` once disconnected.
+    #[cfg(feature = "async")]
+    pub(crate) fn poll_recv(
+        &self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Option<LinkEvent>> {
+        use std::task::Poll;
+
+        let poll = || match self.receiver.try_recv() {
+            Ok(event) => Poll::Ready(Some(event)),
+            Err(TryRecvError::Disconnected) => Poll::Ready(None),
+            Err(TryRecvError::Empty) => Poll::Pending,
+        };
+        if let Poll::Ready(event) = poll() {
+            return Poll::Ready(event);
+        }
+        {
+            let mut waker = self.shared.waker.lock().unwrap();
+            match &*waker {
+                Some(waker) if waker.will_wake(cx.waker()) => {}
+                _ => *waker = Some(cx.waker().clone()),
+            }
+        }
+        // Senders wake the task after sending, so check again for an event, which was
+        // sent before the waker was stored.
+        poll()
+    }
+}
+
+impl AblLink {
+    ///  Receive peer, tempo and start/stop notifications as [LinkEvent]s through
+    ///  an unbounded and lossless channel.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: no
+    ///
+    ///  Unlike the `set_..._callback` functions, this allows any thread to wait for
+    ///  notifications, instead of running code on the Link-managed thread. The channel
+    ///  is fed by callbacks, so the `delete_..._callback` functions stop its notifications
+    ///  as well.
+    pub fn events(&self) -> LinkEvents {
+        self.events_with(EventsConfig::default())
+    }
+
+    ///  Receive peer, tempo and start/stop notifications as [LinkEvent]s through
+    ///  a channel with the given capacity and delivery.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: no
+    ///
+    ///  Panics, if [Delivery::Lossless] is combined with a bounded [Capacity].
+    pub fn events_with(&self, config: EventsConfig) -> LinkEvents {
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("AblLink::events_with");
+        let (sender, receiver) = match config.capacity {
+            Capacity::Unbounded => unbounded(),
+            Capacity::Bounded(capacity) => {
+                assert!(
+                    config.delivery != Delivery::Lossless,
+                    "lossless delivery needs an unbounded channel, use Delivery::Backpressure \
+                     for a bounded one"
+                );
+                bounded(capacity.max(1))
+            }
+        };
+        let (closed_sender, closed) = bounded(0);
+        let shared = Arc::new(Shared {
+            delivery: config.delivery,
+            pending: Mutex::new(receiver.clone()),
+            closed,
+            stalled: AtomicBool::new(false),
+            waker: Mutex::new(None),
+        });
+
+        let num_peers_sender = EventSender {
+            sender,
+            shared: WakeOnDrop(Arc::clone(&shared)),
+        };
+        let tempo_sender = num_peers_sender.clone();
+        let start_stop_sender = num_peers_sender.clone();
+
+        LinkEvents {
+            receiver,
+            #[cfg(feature = "async")]
+            shared,
+            _closed: closed_sender,
+            _subscriptions: [
+                self.set_num_peers_callback(move |num_peers| {
+                    num_peers_sender.send(LinkEvent::PeersChanged(num_peers))
+                }),
+                self.set_tempo_callback(move |tempo| {
+                    tempo_sender.send(LinkEvent::TempoChanged(tempo))
+                }),
+                self.set_start_stop_callback(move |is_playing| {
+                    start_stop_sender.send(LinkEvent::StartStop(is_playing))
+                }),
+            ],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{thread, time::Instant};
+
+    fn events_with(link: &AblLink, capacity: Capacity, delivery: Delivery) -> LinkEvents {
+        link.events_with(EventsConfig { capacity, delivery })
+    }
+
+    #[test]
+    fn lossless_delivers_every_event_in_order() {
+        let link = AblLink::new(120.);
+        let events = link.events();
+        link.notify_tempo(120.);
+        link.notify_num_peers(1);
+        link.notify_tempo(121.);
+        link.notify_start_stop(true);
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                LinkEvent::TempoChanged(120.),
+                LinkEvent::PeersChanged(1),
+                LinkEvent::TempoChanged(121.),
+                LinkEvent::StartStop(true),
+            ]
+        );
+        assert_eq!(events.try_recv(), Err(TryRecvError::Empty));
+    }
+
+    #[test]
+    #[should_panic(expected = "lossless delivery needs an unbounded channel")]
+    fn lossless_delivery_rejects_a_bounded_channel() {
+        let link = AblLink::new(120.);
+        events_with(&link, Capacity::Bounded(4), Delivery::Lossless);
+    }
+
+    #[test]
+    fn receivers_are_disconnected_when_the_link_is_dropped() {
+        let link = AblLink::new(120.);
+        let events = link.events();
+        link.notify_tempo(99.);
+        drop(link);
+        assert_eq!(events.recv(), Ok(LinkEvent::TempoChanged(99.)));
+        assert_eq!(events.recv(), Err(RecvError));
+    }
+
+    #[test]
+    fn coalescing_keeps_the_latest_event_of_each_kind() {
+        let link = AblLink::new(120.);
+        let events = events_with(&link, Capacity::Unbounded, Delivery::Coalescing);
+        link.notify_tempo(120.);
+        link.notify_num_peers(1);
+        link.notify_tempo(121.);
+        link.notify_start_stop(true);
+        link.notify_num_peers(2);
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [
+                LinkEvent::TempoChanged(121.),
+                LinkEvent::PeersChanged(2),
+                LinkEvent::StartStop(true),
+            ]
+        );
+    }
+
+    #[test]
+    fn coalescing_drops_the_oldest_event_of_a_full_channel() {
+        let link = AblLink::new(120.);
+        let events = events_with(&link, Capacity::Bounded(2), Delivery::Coalescing);
+        link.notify_tempo(120.);
+        link.notify_num_peers(1);
+        link.notify_start_stop(true);
+        link.notify_num_peers(2);
+
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [LinkEvent::PeersChanged(2), LinkEvent::StartStop(true)]
+        );
+    }
+
+    #[test]
+    fn backpressure_waits_for_the_receiver() {
+        let link = AblLink::new(120.);
+        let delivery = Delivery::Backpressure {
+            timeout: Duration::from_secs(60),
+        };
+        let events = events_with(&link, Capacity::Bounded(1), delivery);
+        link.notify_tempo(120.);
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(20));
+                assert_eq!(events.recv(), Ok(LinkEvent::TempoChanged(120.)));
+            });
+            link.notify_tempo(121.);
+        });
+        assert_eq!(events.try_recv(), Ok(LinkEvent::TempoChanged(121.)));
+    }
+
+    #[test]
+    fn backpressure_coalesces_while_the_receiver_is_stalled() {
+        let link = AblLink::new(120.);
+        let timeout = Duration::from_millis(20);
+        let events = events_with(
+            &link,
+            Capacity::Bounded(2),
+            Delivery::Backpressure { timeout },
+        );
+        link.notify_tempo(120.);
+        link.notify_num_peers(1);
+
+        // Only the first notification into the full channel waits for the timeout.
+        let start = Instant::now();
+        link.notify_tempo(121.);
+        link.notify_start_stop(true);
+        link.notify_start_stop(false);
+        assert!(start.elapsed() >= timeout);
+
+        // Once the receiver made room, every event is delivered again.
+        assert_eq!(events.try_recv(), Ok(LinkEvent::PeersChanged(1)));
+        link.notify_start_stop(true);
+        assert_eq!(
+            events.try_iter().collect::<Vec<_>>(),
+            [LinkEvent::StartStop(false), LinkEvent::StartStop(true)]
+        );
+    }
+
+    #[test]
+    fn dropping_the_receiver_releases_a_waiting_sender() {
+        let link = AblLink::new(120.);
+        let delivery = Delivery::Backpressure {
+            timeout: Duration::from_secs(3600),
+        };
+        let events = events_with(&link, Capacity::Bounded(1), delivery);
+        link.notify_tempo(120.);
+
+        thread::scope(|scope| {
+            let sender = scope.spawn(|| link.notify_tempo(121.));
+            thread::sleep(Duration::from_millis(20));
+            drop(events);
+            sender.join().unwrap();
+        });
+    }
+
+    #[test]
+    fn receiver_can_be_selected_with_other_channels() {
+        let link = AblLink::new(120.);
+        let events = link.events();
+        let (_other_sender, other) = unbounded::<()>();
+        link.notify_num_peers(3);
+
+        select! {
+            recv(events.receiver()) -> event => {
+                assert_eq!(event, Ok(LinkEvent::PeersChanged(3)));
+            }
+            recv(other) -> _ => panic!("no message was sent"),
+        }
+    }
+}
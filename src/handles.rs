@@ -0,0 +1,227 @@
+#[cfg(feature = "rt-check")]
+use crate::rt_check;
+use crate::{AblLink, Micros, SessionState, abl_link::LinkInner, rust_bindings::*};
+use std::{
+    cell::Cell,
+    marker::PhantomData,
+    ops::Deref,
+    sync::{Arc, Weak, atomic::Ordering},
+};
+
+/// The only owner of the audio thread functions of an [AblLink].
+///
+/// Only one AudioThreadHandle exists per [AblLink] at a time. It can be moved into the
+/// audio thread, but it is not `Sync`, so it can not be shared between threads. That way
+/// the audio capture and commit functions, which are not thread-safe, can not be called
+/// from two threads at once.
+///
+/// The handle keeps the Link instance alive, even if the [AblLink] is dropped first.
+///
+/// ```compile_fail
+/// # use rusty_link::AblLink;
+/// let link = AblLink::new(120.);
+/// let handle = link.audio_thread_handle().unwrap();
+/// std::thread::scope(|scope| {
+///     // A reference to the handle can not be sent to another thread.
+///     scope.spawn(|| handle.clock_micros());
+///     handle.clock_micros();
+/// });
+/// ```
+pub struct AudioThreadHandle {
+    inner: Arc<LinkInner>,
+    #[cfg(feature = "rt-check")]
+    audio_thread: rt_check::AudioThread,
+    // Opts out of `Sync`, but keeps `Send`.
+    _not_sync: PhantomData<Cell<()>>,
+}
+
+impl Drop for AudioThreadHandle {
+    fn drop(&mut self) {
+        self.inner
+            .audio_thread_handle_taken
+            .store(false, Ordering::Release);
+    }
+}
+
+impl AudioThreadHandle {
+    /// Get the current link clock time in microseconds.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: yes
+    pub fn clock_micros(&self) -> i64 {
+        unsafe { abl_link_clock_micros(self.inner.link) }
+    }
+
+    /// Get the current link clock time. Typed variant of [AudioThreadHandle::clock_micros].
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: yes
+    pub fn clock(&self) -> Micros {
+        Micros::new(self.clock_micros())
+    }
+
+    ///  Capture the current Link Session State from the audio thread.
+    ///
+    ///  Thread-safe: no
+    ///
+    ///  Realtime-safe: yes
+    ///
+    ///  This function should ONLY be called in the audio thread and must not be
+    ///  accessed from any other threads. After capturing the SessionState holds a snapshot
+    ///  of the current Link Session State, so it should be used in a local scope. The
+    ///  session_state should not be created on the audio thread.
+    pub fn capture_audio_session_state(&self, session_state: &mut SessionState) {
+        #[cfg(feature = "rt-check")]
+        self.audio_thread
+            .register("AudioThreadHandle::capture_audio_session_state");
+        unsafe {
+            abl_link_capture_audio_session_state(self.inner.link, session_state.session_state)
+        }
+    }
+
+    ///  Commit the given Session State to the Link session from the audio thread.
+    ///
+    ///  Thread-safe: no
+    ///
+    ///  Realtime-safe: yes
+    ///
+    ///  This function should ONLY be called in the audio thread. The given
+    ///  session_state will replace the current Link state. Modifications will be
+    ///  communicated to other peers in the session.
+    pub fn commit_audio_session_state(&self, session_state: &SessionState) {
+        #[cfg(feature = "rt-check")]
+        self.audio_thread
+            .check("AudioThreadHandle::commit_audio_session_state");
+        unsafe {
+            abl_link_commit_audio_session_state(self.inner.link, session_state.session_state)
+        };
+    }
+}
+
+/// A cloneable handle to all app thread functions of an [AblLink], which can be
+/// shared between any number of threads.
+///
+/// The handle keeps the Link instance alive, even if the [AblLink] is dropped first.
+/// So an AppHandle, which is moved into a callback of the same instance, keeps the
+/// instance and the callback alive forever. Move a [WeakAppHandle] into the callback
+/// instead.
+pub struct AppHandle {
+    link: AblLink,
+}
+
+impl AppHandle {
+    /// Get a handle, which does not keep the Link instance alive.
+    pub fn downgrade(&self) -> WeakAppHandle {
+        WeakAppHandle {
+            inner: Arc::downgrade(&self.link.inner),
+        }
+    }
+}
+
+impl Clone for AppHandle {
+    fn clone(&self) -> Self {
+        self.link.app_handle()
+    }
+}
+
+impl Deref for AppHandle {
+    type Target = AblLink;
+
+    fn deref(&self) -> &AblLink {
+        &self.link
+    }
+}
+
+/// A handle to the app thread functions of an [AblLink], which does not keep the Link
+/// instance alive. Created by [AppHandle::downgrade].
+#[derive(Clone)]
+pub struct WeakAppHandle {
+    inner: Weak<LinkInner>,
+}
+
+impl WeakAppHandle {
+    /// Get an [AppHandle], if the Link instance still exists.
+    pub fn upgrade(&self) -> Option<AppHandle> {
+        self.inner.upgrade().map(|inner| AppHandle {
+            link: AblLink { inner },
+        })
+    }
+}
+
+impl AblLink {
+    ///  Get the handle to the audio thread functions. Returns `None`, while another
+    ///  [AudioThreadHandle] of this instance exists.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: yes
+    pub fn audio_thread_handle(&self) -> Option<AudioThreadHandle> {
+        self.inner
+            .audio_thread_handle_taken
+            .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+            .ok()
+            .map(|_| AudioThreadHandle {
+                inner: Arc::clone(&self.inner),
+                #[cfg(feature = "rt-check")]
+                audio_thread: rt_check::AudioThread::default(),
+                _not_sync: PhantomData,
+            })
+    }
+
+    ///  Get a cloneable handle to the app thread functions.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: yes
+    pub fn app_handle(&self) -> AppHandle {
+        AppHandle {
+            link: AblLink {
+                inner: Arc::clone(&self.inner),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_one_audio_thread_handle_exists_at_a_time() {
+        let link = AblLink::new(120.);
+        let handle = link.audio_thread_handle();
+        assert!(handle.is_some());
+        assert!(link.audio_thread_handle().is_none());
+        drop(handle);
+        assert!(link.audio_thread_handle().is_some());
+    }
+
+    #[test]
+    fn audio_thread_handle_is_taken_across_app_handles() {
+        let link = AblLink::new(120.);
+        let app_handle = link.app_handle();
+        let handle = app_handle.audio_thread_handle();
+        assert!(handle.is_some());
+        assert!(link.audio_thread_handle().is_none());
+        drop(handle);
+        assert!(link.audio_thread_handle().is_some());
+    }
+
+    #[test]
+    fn weak_app_handle_does_not_keep_the_link_alive() {
+        let link = AblLink::new(120.);
+        let weak = link.app_handle().downgrade();
+        assert!(weak.upgrade().is_some());
+
+        // A callback with a weak handle does not keep the instance alive.
+        let callback_handle = weak.clone();
+        link.set_tempo_callback(move |_| {
+            let _ = callback_handle.upgrade();
+        })
+        .detach();
+        drop(link);
+        assert!(weak.upgrade().is_none());
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{Clock, HostTimeFilter};
+
+/// Estimates the host time of the current audio callback from the jittery host time at
+/// which it was called and the sample clock.
+///
+/// Implemented by the linear regression of [HostTimeFilter], the [DelayLockedLoop] and
+/// the [KalmanFilter](crate::KalmanFilter). The trait is object safe, so the strategy can
+/// be chosen at runtime with a `Box<dyn HostTimeEstimator + Send>`. All implementations
+/// are realtime-safe.
+///
+/// [DelayLockedLoop]: crate::DelayLockedLoop
+pub trait HostTimeEstimator {
+    /// The filtered host time in microseconds of the given sample clock, which is usually
+    /// the number of samples processed before the current callback.
+    fn sample_time_to_host_time(&mut self, clock_micros: i64, sample_clock: u64) -> i64;
+
+    /// Forget all previous points, for example after the audio device was restarted.
+    fn reset(&mut self);
+
+    /// Like [HostTimeEstimator::sample_time_to_host_time], but reads the current host
+    /// time from the given [Clock].
+    fn sample_time_to_host_time_with(&mut self, clock: &dyn Clock, sample_clock: u64) -> i64 {
+        self.sample_time_to_host_time(clock.clock_micros(), sample_clock)
+    }
+}
+
+impl<E: HostTimeEstimator + ?Sized> HostTimeEstimator for Box<E> {
+    fn sample_time_to_host_time(&mut self, clock_micros: i64, sample_clock: u64) -> i64 {
+        (**self).sample_time_to_host_time(clock_micros, sample_clock)
+    }
+
+    fn reset(&mut self) {
+        (**self).reset()
+    }
+}
+
+impl HostTimeEstimator for HostTimeFilter {
+    fn sample_time_to_host_time(&mut self, clock_micros: i64, sample_clock: u64) -> i64 {
+        HostTimeFilter::sample_time_to_host_time(self, clock_micros, sample_clock)
+    }
+
+    fn reset(&mut self) {
+        HostTimeFilter::reset(self)
+    }
+}
@@ -1,12 +1,155 @@
+use std::time::Duration;
+
+// Outliers are only rejected, once the window holds this many points.
+const MIN_POINTS_FOR_REJECTION: usize = 16;
+// The estimate counts as reliable, once the window is filled this much, and holds at
+// least MIN_POINTS_FOR_REJECTION points, or is full, if it is smaller.
+const MIN_RELIABLE_FILL: f64 = 0.25;
+// The regression sums fit into an i128, as long as the number of points times the
+// distance between two points in the window stays below this, on both clocks.
+const MAX_POINTS_TIMES_SPAN: i128 = 1 << 62;
+
+/// The points in time a [HostTimeFilter] fits its line through.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FilterWindow {
+    /// The given number of most recent points (at least 1).
+    Points(usize),
+    /// The points of the given duration of host time, but at most `max_points`
+    /// (at least 1), for which the buffer is allocated up front.
+    Duration {
+        duration: Duration,
+        max_points: usize,
+    },
+}
+
+impl Default for FilterWindow {
+    fn default() -> Self {
+        FilterWindow::Points(512)
+    }
+}
+
+/// What a [HostTimeFilter] returns while its window is still filling up.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum WarmUp {
+    /// The line through the points collected so far.
+    #[default]
+    Regression,
+    /// The unfiltered host time, until the window holds at least this many points.
+    PassThrough(usize),
+}
+
+/// How a [HostTimeFilter] finds the slope of its line.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum FilterSlope {
+    /// Fit the slope to the points, which follows the actual rate of the sample clock.
+    #[default]
+    Fitted,
+    /// Fix the slope to the given nominal sample rate in Hz, and only fit the offset.
+    Nominal(f64),
+}
+
+/// How a [HostTimeFilter] treats points, which do not fit the line through the previous
+/// points, like callbacks after an xrun or after the audio device was reset.
+///
+/// Rejection is off by default, so the filter keeps the behaviour of the C++
+/// HostTimeFilter. A threshold of 2 ms with `reset_after: 4` works well for most devices.
+/// Either way, the filter starts over, if the sample clock goes backwards, for example
+/// after the audio device was restarted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutlierRejection {
+    /// Keep all points.
+    #[default]
+    Off,
+    /// Discard points, whose host time is further than `threshold` off the line, once the
+    /// window holds 16 points, so windows of fewer points keep all points. After
+    /// `reset_after` (at least 1) discarded points in a row, the timing is assumed to have
+    /// changed for good, and the filter starts over.
+    Threshold {
+        threshold: Duration,
+        reset_after: usize,
+    },
+}
+
+/// Configuration of a [HostTimeFilter], see [HostTimeFilter::with_config].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct HostTimeFilterConfig {
+    pub window: FilterWindow,
+    pub warm_up: WarmUp,
+    pub slope: FilterSlope,
+    pub outlier_rejection: OutlierRejection,
+}
+
+/// Statistics about the timing of a [HostTimeFilter], see [HostTimeFilter::diagnostics].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FilterDiagnostics {
+    /// The rate of the sample clock measured against the host clock in Hz, or `None`
+    /// before the window holds two points with different sample times.
+    pub fitted_sample_rate: Option<f64>,
+    /// The root mean square of the distance between the points and the line in µs.
+    pub rms_jitter_micros: f64,
+    /// The number of points in the window.
+    pub points: usize,
+    /// How much of the window is filled, from 0 to 1. For a [FilterWindow::Duration],
+    /// this is the larger of the share of the duration and of `max_points`.
+    pub fill: f64,
+    /// The host time between the oldest and the newest point in the window.
+    pub span: Duration,
+    /// True, once the window holds enough points for a reliable estimate.
+    pub is_reliable: bool,
+    pub last_event: FilterEvent,
+    pub counters: FilterCounters,
+}
+
+impl FilterDiagnostics {
+    /// How much faster the sample clock runs than the given nominal sample rate, in parts
+    /// per million of the host clock. Negative, if it runs slower.
+    pub fn drift_ppm(&self, nominal_sample_rate: f64) -> Option<f64> {
+        self.fitted_sample_rate
+            .map(|sample_rate| (sample_rate / nominal_sample_rate - 1.) * 1e6)
+    }
+}
+
+/// What a [HostTimeFilter] did with the last point.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum FilterEvent {
+    /// The point was added to the window.
+    #[default]
+    Accepted,
+    /// The point was an outlier and was discarded. The host time was estimated from the
+    /// previous points.
+    Discarded,
+    /// The point did not fit the previous points for too long, the sample clock went
+    /// backwards, or one of the clocks jumped too far to fit the point into the window.
+    /// The filter started over with this point.
+    Reset,
+}
+
+/// How often a [HostTimeFilter] discarded points and started over by itself, since it
+/// was created.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FilterCounters {
+    pub discarded: u64,
+    pub resets: u64,
+}
+
 /// Audio callbacks are not always called at a perfectly regular interval, introducing jitter.
 /// The HostTimeFilter utility struct performs a linear regression between
 /// system time and sample time in order to improve the accuracy of system
 /// time values used in the audio callback. On Windows, Ableton recommends using
 /// ASIO (better timing accuracy and lower latency).
 pub struct HostTimeFilter {
+    config: HostTimeFilterConfig,
+    // Ring buffer, which is allocated once. The points are at `first..first + len`.
     points_buffer: Vec<TimeDataPoint>,
-    max_buffer_size: usize,
-    index: usize,
+    first: usize,
+    len: usize,
+    // The points are summed up relative to the newest point, so the sums stay small over
+    // days of uptime.
+    origin: TimeDataPoint,
+    sums: Sums,
+    consecutive_outliers: usize,
+    last_event: FilterEvent,
+    counters: FilterCounters,
 }
 
 impl Default for HostTimeFilter {
@@ -16,100 +159,674 @@ impl Default for HostTimeFilter {
 }
 
 impl HostTimeFilter {
+    /// Create a filter over the last 512 points.
     pub fn new() -> Self {
-        let max_buffer_size: usize = 512; // Default
+        Self::with_config(HostTimeFilterConfig::default())
+    }
+
+    /// Create a filter with the given window, warm-up, slope and outlier rejection.
+    /// Allocates the buffer for the whole window.
+    ///
+    /// Panics, if a nominal sample rate is not finite and positive.
+    pub fn with_config(config: HostTimeFilterConfig) -> Self {
+        if let FilterSlope::Nominal(sample_rate) = config.slope {
+            assert!(
+                sample_rate.is_finite() && sample_rate > 0.,
+                "Nominal sample rate must be finite and positive."
+            );
+        }
+        let max_buffer_size = match config.window {
+            FilterWindow::Points(points) => points,
+            FilterWindow::Duration { max_points, .. } => max_points,
+        }
+        .max(1);
         Self {
-            points_buffer: Vec::with_capacity(max_buffer_size),
-            max_buffer_size,
-            index: 0,
+            config,
+            points_buffer: vec![TimeDataPoint::new(0, 0); max_buffer_size],
+            first: 0,
+            len: 0,
+            origin: TimeDataPoint::new(0, 0),
+            sums: Sums::default(),
+            consecutive_outliers: 0,
+            last_event: FilterEvent::Accepted,
+            counters: FilterCounters::default(),
         }
     }
 
-    /// Reset internal buffer of [HostTimeFilter]
+    /// The configuration of this filter.
+    pub fn config(&self) -> &HostTimeFilterConfig {
+        &self.config
+    }
+
+    /// Reset internal buffer of [HostTimeFilter]. Does not allocate, so it can be called
+    /// from the audio thread.
     pub fn reset(&mut self) {
-        self.points_buffer = Vec::with_capacity(self.max_buffer_size);
-        self.index = 0;
+        self.first = 0;
+        self.len = 0;
+        self.sums = Sums::default();
+        self.consecutive_outliers = 0;
+    }
+
+    /// What the filter did with the last point. Can be polled after each call of
+    /// [HostTimeFilter::sample_time_to_host_time], for example to log glitches.
+    pub fn last_event(&self) -> FilterEvent {
+        self.last_event
+    }
+
+    /// How often the filter discarded points and started over by itself. Not cleared by
+    /// [HostTimeFilter::reset].
+    pub fn counters(&self) -> FilterCounters {
+        self.counters
+    }
+
+    /// Statistics about the points in the window. Takes constant time and does not
+    /// allocate, so it can be called from the audio thread, and the result can be copied
+    /// to another thread.
+    pub fn diagnostics(&self) -> FilterDiagnostics {
+        let mut diagnostics = FilterDiagnostics {
+            points: self.len,
+            last_event: self.last_event,
+            counters: self.counters,
+            ..Default::default()
+        };
+        if self.len == 0 {
+            return diagnostics;
+        }
+
+        let fitted_slope = self.sums.slope();
+        if fitted_slope > 0. {
+            diagnostics.fitted_sample_rate = Some(1e6 / fitted_slope);
+        }
+        let (slope, _) = self.line();
+        diagnostics.rms_jitter_micros = self.sums.rms_residual(slope);
+
+        let oldest = self.points_buffer[self.first];
+        diagnostics.span =
+            Duration::from_micros(self.origin.host_clock.saturating_sub(oldest.host_clock) as u64);
+        let point_fill = self.len as f64 / self.points_buffer.len() as f64;
+        diagnostics.fill = match self.config.window {
+            FilterWindow::Points(_) => point_fill,
+            FilterWindow::Duration { duration, .. } if !duration.is_zero() => point_fill
+                .max(diagnostics.span.as_secs_f64() / duration.as_secs_f64())
+                .min(1.),
+            FilterWindow::Duration { .. } => 1.,
+        };
+        let min_reliable_points = MIN_POINTS_FOR_REJECTION.min(self.points_buffer.len());
+        diagnostics.is_reliable =
+            self.len >= min_reliable_points && diagnostics.fill >= MIN_RELIABLE_FILL;
+        diagnostics
     }
 
     /// Performs a linear regression between system time and sample time in order
     /// to improve the accuracy of system time values. Usually used in the audio callback.
+    ///
+    /// The regression sums are updated with each new point, so the cost does not depend
+    /// on the size of the window.
     pub fn sample_time_to_host_time(&mut self, clock_micros: i64, sample_clock: u64) -> i64 {
         assert!(clock_micros >= 0, "Negative clock values unsupported.");
 
         // Make a pair struct of the current sample time and corresponding clock_micros host time to add to the buffer
-        let point = TimeDataPoint::new(sample_clock as u128, clock_micros as u128);
+        let point = TimeDataPoint::new(sample_clock, clock_micros);
 
-        // Fill buffer, then keep recycling it by adding new values at index
-        if self.points_buffer.len() < self.max_buffer_size {
-            self.points_buffer.push(point);
-        } else {
-            self.points_buffer[self.index] = point;
+        self.last_event = self.classify(&point);
+        match self.last_event {
+            FilterEvent::Accepted => {}
+            FilterEvent::Discarded => {
+                self.counters.discarded += 1;
+                return self.estimate(&point);
+            }
+            FilterEvent::Reset => {
+                self.counters.resets += 1;
+                self.reset();
+            }
+        }
+        self.push(point);
+
+        if let WarmUp::PassThrough(points) = self.config.warm_up
+            && self.len < points
+        {
+            return clock_micros;
         }
-        self.index = (self.index + 1) % self.max_buffer_size;
 
-        // Calculate a line based on time data points currently in buffer
-        let line = Self::linear_regression(&self.points_buffer);
+        self.estimate(&point)
+    }
 
-        // Apply line to current sample time to get a filtered clock time in micros
-        let filtered_clock_micros = line.slope * sample_clock as f64 + line.intercept;
+    // Calculate a line based on time data points currently in buffer, and apply it to the
+    // sample time of the point to get a filtered clock time in micros
+    fn estimate(&self, point: &TimeDataPoint) -> i64 {
+        let (x, _) = point.relative_to(&self.origin);
+        let (slope, intercept) = self.line();
+        let filtered_clock_micros = intercept + slope * x as f64;
 
         // Return result in i64 clock_micros() format
-        filtered_clock_micros.round() as i64
+        self.origin.host_clock + filtered_clock_micros.round() as i64
     }
 
-    /// Simple liner regression from a buffer of points in time on 2 different clocks. Math in microseconds
-    /// can easily overflow a i64 or even a u64 when summing up lots of multiplications. Hence I am
-    /// trying u128 here (Same as what Duration uses internally) to avoid that and to maintain accuracy.
-    fn linear_regression(buffer: &Vec<TimeDataPoint>) -> Line {
-        let num_points = buffer.len() as u128;
-        assert!(num_points > 0, "Provide at least one TimeDataPoint.");
+    // Slope and intercept of the line, relative to the origin
+    fn line(&self) -> (f64, f64) {
+        let slope = match self.config.slope {
+            FilterSlope::Fitted => self.sums.slope(),
+            FilterSlope::Nominal(sample_rate) => 1e6 / sample_rate,
+        };
+        (slope, self.sums.intercept(slope))
+    }
 
-        let mut sum_x: u128 = 0;
-        let mut sum_xx: u128 = 0;
-        let mut sum_xy: u128 = 0;
-        let mut sum_y: u128 = 0;
+    fn classify(&mut self, point: &TimeDataPoint) -> FilterEvent {
+        // Always checked, as the line through points before and after a restart of the
+        // sample clock is meaningless, and the sums would overflow after huge jumps.
+        // The origin is the newest point.
+        if self.len > 0
+            && (point.sample_clock < self.origin.sample_clock || !self.fits_into_window(point))
+        {
+            return FilterEvent::Reset;
+        }
+        let OutlierRejection::Threshold {
+            threshold,
+            reset_after,
+        } = self.config.outlier_rejection
+        else {
+            return FilterEvent::Accepted;
+        };
+        // The line through the first few points is not reliable enough yet
+        if self.len < MIN_POINTS_FOR_REJECTION {
+            return FilterEvent::Accepted;
+        }
 
-        for p in buffer {
-            sum_x += p.sample_clock;
-            sum_xx += p.sample_clock * p.sample_clock;
-            sum_xy += p.sample_clock * p.host_clock;
-            sum_y += p.host_clock;
+        let residual = point.host_clock - self.estimate(point);
+        if residual.unsigned_abs() as u128 <= threshold.as_micros() {
+            self.consecutive_outliers = 0;
+            return FilterEvent::Accepted;
+        }
+        self.consecutive_outliers += 1;
+        if self.consecutive_outliers >= reset_after.max(1) {
+            FilterEvent::Reset
+        } else {
+            FilterEvent::Discarded
         }
+    }
 
-        let denominator = num_points * sum_xx - sum_x * sum_x;
+    // True, if the point is close enough to the points in the window, that the sums can
+    // not overflow with it. Fails after jumps of days or more of the clocks.
+    fn fits_into_window(&self, point: &TimeDataPoint) -> bool {
+        let max_span = MAX_POINTS_TIMES_SPAN / self.points_buffer.len() as i128;
+        [self.points_buffer[self.first], self.origin]
+            .iter()
+            .all(|other| {
+                let (x, y) = point.relative_to(other);
+                x.abs() < max_span && y.abs() < max_span
+            })
+    }
 
-        let slope = match denominator {
-            0 => 0.0,
-            _ => (num_points * sum_xy - sum_x * sum_y) as f64 / denominator as f64,
-        };
+    fn push(&mut self, point: TimeDataPoint) {
+        // Fill buffer, then keep recycling it by replacing the oldest point
+        if self.len == self.points_buffer.len() {
+            self.evict_oldest();
+        }
+        let index = (self.first + self.len) % self.points_buffer.len();
+        self.points_buffer[index] = point;
+        self.len += 1;
 
-        let intercept = (sum_y as f64 - slope * sum_x as f64) / num_points as f64;
+        // Move the origin to the new point
+        if self.len == 1 {
+            self.origin = point;
+        }
+        let (x, y) = point.relative_to(&self.origin);
+        self.sums.add(x, y);
+        self.sums.shift(x, y);
+        self.origin = point;
 
-        Line::new(slope, intercept)
+        if let FilterWindow::Duration { duration, .. } = self.config.window {
+            let oldest_allowed = point
+                .host_clock
+                .saturating_sub(duration.as_micros().min(i64::MAX as u128) as i64);
+            while self.len > 1 && self.points_buffer[self.first].host_clock < oldest_allowed {
+                self.evict_oldest();
+            }
+        }
+    }
+
+    fn evict_oldest(&mut self) {
+        let (x, y) = self.points_buffer[self.first].relative_to(&self.origin);
+        self.sums.remove(x, y);
+        self.first = (self.first + 1) % self.points_buffer.len();
+        self.len -= 1;
     }
 }
 
+#[derive(Clone, Copy)]
 struct TimeDataPoint {
-    sample_clock: u128,
-    host_clock: u128,
+    sample_clock: u64,
+    host_clock: i64,
 }
 
 impl TimeDataPoint {
-    fn new(sample_clock: u128, host_clock: u128) -> Self {
+    fn new(sample_clock: u64, host_clock: i64) -> Self {
         Self {
             sample_clock,
             host_clock,
         }
     }
+
+    fn relative_to(&self, origin: &TimeDataPoint) -> (i128, i128) {
+        (
+            self.sample_clock as i128 - origin.sample_clock as i128,
+            self.host_clock as i128 - origin.host_clock as i128,
+        )
+    }
+}
+
+/// Running sums for a simple linear regression between points in time on 2 different
+/// clocks. Math in microseconds can easily overflow a i64 when summing up lots of
+/// multiplications, hence i128. The sums are exact integers, so adding and removing
+/// points does not accumulate rounding errors, and the result is the same as summing up
+/// the whole buffer.
+#[derive(Clone, Copy, Default)]
+struct Sums {
+    n: i128,
+    x: i128,
+    y: i128,
+    xx: i128,
+    xy: i128,
+    yy: i128,
 }
 
-struct Line {
-    slope: f64,
-    intercept: f64,
+impl Sums {
+    fn add(&mut self, x: i128, y: i128) {
+        self.n += 1;
+        self.x += x;
+        self.y += y;
+        self.xx += x * x;
+        self.xy += x * y;
+        self.yy += y * y;
+    }
+
+    fn remove(&mut self, x: i128, y: i128) {
+        self.n -= 1;
+        self.x -= x;
+        self.y -= y;
+        self.xx -= x * x;
+        self.xy -= x * y;
+        self.yy -= y * y;
+    }
+
+    /// Move the origin of all summed points to (dx, dy).
+    fn shift(&mut self, dx: i128, dy: i128) {
+        // Sum of (x - dx)(y - dy) = xy - dx * y - dy * x + n * dx * dy, and so on.
+        self.xx += -2 * dx * self.x + self.n * dx * dx;
+        self.xy += -dx * self.y - dy * self.x + self.n * dx * dy;
+        self.yy += -2 * dy * self.y + self.n * dy * dy;
+        self.x -= self.n * dx;
+        self.y -= self.n * dy;
+    }
+
+    /// The slope of the line through the points.
+    fn slope(&self) -> f64 {
+        let denominator = self.n * self.xx - self.x * self.x;
+
+        match denominator {
+            0 => 0.0,
+            _ => (self.n * self.xy - self.x * self.y) as f64 / denominator as f64,
+        }
+    }
+
+    /// The intercept of the line with the given slope through the mean of the points.
+    fn intercept(&self, slope: f64) -> f64 {
+        assert!(self.n > 0, "Provide at least one TimeDataPoint.");
+
+        (self.y as f64 - slope * self.x as f64) / self.n as f64
+    }
+
+    /// The root mean square of the distance of the points from the line with the given
+    /// slope through the mean of the points.
+    fn rms_residual(&self, slope: f64) -> f64 {
+        assert!(self.n > 0, "Provide at least one TimeDataPoint.");
+
+        // Sums of squares around the mean. The numerators are exact integers.
+        let n = self.n as f64;
+        let sxx = (self.n * self.xx - self.x * self.x) as f64 / n;
+        let sxy = (self.n * self.xy - self.x * self.y) as f64 / n;
+        let syy = (self.n * self.yy - self.y * self.y) as f64 / n;
+
+        let residual_sum = syy - 2. * slope * sxy + slope * slope * sxx;
+        (residual_sum.max(0.) / n).sqrt()
+    }
 }
 
-impl Line {
-    fn new(slope: f64, intercept: f64) -> Self {
-        Self { slope, intercept }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feeds points of a 48 kHz clock with 512 sample buffers, starting at the given times.
+    fn feed(filter: &mut HostTimeFilter, start_sample: u64, start_host: i64, buffers: u64) {
+        for i in 0..buffers {
+            let samples = i * 512;
+            let host = start_host + (samples * 1_000_000 / 48_000) as i64;
+            filter.sample_time_to_host_time(host, start_sample + samples);
+        }
+    }
+
+    #[test]
+    fn duration_window_evicts_old_points() {
+        let mut filter = HostTimeFilter::with_config(HostTimeFilterConfig {
+            window: FilterWindow::Duration {
+                duration: Duration::from_millis(100),
+                max_points: 1000,
+            },
+            ..Default::default()
+        });
+        // 512 samples at 48 kHz are 10.67 ms, so 100 ms hold 10 buffers.
+        feed(&mut filter, 0, 0, 100);
+        let diagnostics = filter.diagnostics();
+        assert_eq!(diagnostics.points, 10);
+        assert_eq!(diagnostics.span, Duration::from_micros(96_000));
+        assert_eq!(diagnostics.fill, 0.96);
+    }
+
+    #[test]
+    fn duration_window_holds_at_most_max_points() {
+        let mut filter = HostTimeFilter::with_config(HostTimeFilterConfig {
+            window: FilterWindow::Duration {
+                duration: Duration::from_secs(10),
+                max_points: 4,
+            },
+            ..Default::default()
+        });
+        feed(&mut filter, 0, 0, 100);
+        let diagnostics = filter.diagnostics();
+        assert_eq!(diagnostics.points, 4);
+        // Three buffers of 10.67 ms.
+        assert_eq!(diagnostics.span, Duration::from_micros(32_000));
+        assert_eq!(diagnostics.fill, 1.);
+    }
+
+    #[test]
+    fn pass_through_warm_up_returns_the_raw_clock() {
+        let mut filter = HostTimeFilter::with_config(HostTimeFilterConfig {
+            warm_up: WarmUp::PassThrough(8),
+            ..Default::default()
+        });
+        // Points with jitter, which the regression smooths out.
+        let jitter = |i: u64| if i.is_multiple_of(2) { 300 } else { -300 };
+        let host = |i: u64| 1_000_000 + (i * 10_000) as i64 + jitter(i);
+        for i in 0..7 {
+            assert_eq!(filter.sample_time_to_host_time(host(i), i * 480), host(i));
+        }
+        let filtered = filter.sample_time_to_host_time(host(7), 7 * 480);
+        assert_ne!(filtered, host(7));
+        assert!((filtered - (host(7) - jitter(7))).abs() < 300);
+    }
+
+    #[test]
+    fn nominal_slope_ignores_the_fitted_rate() {
+        let mut filter = HostTimeFilter::with_config(HostTimeFilterConfig {
+            slope: FilterSlope::Nominal(48_000.),
+            ..Default::default()
+        });
+        // A device, which runs at 44.1 kHz instead.
+        let points: Vec<(u64, i64)> = (0..65).map(|i| (i * 441, (i * 10_000) as i64)).collect();
+        let mut filtered = 0;
+        for &(samples, host) in &points {
+            filtered = filter.sample_time_to_host_time(host, samples);
+        }
+        // The line with the nominal slope through the mean of the points.
+        let slope = 1e6 / 48_000.;
+        let offset = points
+            .iter()
+            .map(|&(samples, host)| host as f64 - samples as f64 * slope)
+            .sum::<f64>()
+            / points.len() as f64;
+        let expected = (offset + 64. * 441. * slope).round() as i64;
+        assert_eq!(filtered, expected);
+        let fitted = filter.diagnostics().fitted_sample_rate.unwrap();
+        assert!((fitted - 44_100.).abs() < 1e-6, "{fitted}");
+    }
+
+    #[test]
+    #[should_panic(expected = "Nominal sample rate must be finite and positive.")]
+    fn nominal_slope_must_be_positive() {
+        HostTimeFilter::with_config(HostTimeFilterConfig {
+            slope: FilterSlope::Nominal(0.),
+            ..Default::default()
+        });
+    }
+
+    #[test]
+    fn reset_does_not_allocate() {
+        let mut filter = HostTimeFilter::with_config(HostTimeFilterConfig {
+            window: FilterWindow::Points(64),
+            ..Default::default()
+        });
+        let buffer = filter.points_buffer.as_ptr();
+        let capacity = filter.points_buffer.capacity();
+        feed(&mut filter, 0, 0, 200);
+        filter.reset();
+        assert_eq!(filter.diagnostics().points, 0);
+        feed(&mut filter, 0, 0, 200);
+        assert_eq!(filter.points_buffer.as_ptr(), buffer);
+        assert_eq!(filter.points_buffer.capacity(), capacity);
+        assert_eq!(filter.points_buffer.len(), 64);
+    }
+
+    fn threshold(reset_after: usize) -> HostTimeFilterConfig {
+        HostTimeFilterConfig {
+            outlier_rejection: OutlierRejection::Threshold {
+                threshold: Duration::from_millis(2),
+                reset_after,
+            },
+            ..Default::default()
+        }
+    }
+
+    // The host time of a 48 kHz clock with 512 sample buffers, like in feed.
+    fn host_of(samples: u64) -> i64 {
+        (samples * 1_000_000 / 48_000) as i64
+    }
+
+    #[test]
+    fn outliers_are_discarded() {
+        let mut filter = HostTimeFilter::with_config(threshold(4));
+        feed(&mut filter, 0, 0, 32);
+        let samples = 32 * 512;
+        let estimate = filter.sample_time_to_host_time(host_of(samples) + 5_000, samples);
+        assert_eq!(filter.last_event(), FilterEvent::Discarded);
+        assert!((estimate - host_of(samples)).abs() <= 1);
+        assert_eq!(filter.diagnostics().points, 32);
+
+        // A point on the line again clears the run of outliers.
+        let samples = 33 * 512;
+        filter.sample_time_to_host_time(host_of(samples), samples);
+        assert_eq!(filter.last_event(), FilterEvent::Accepted);
+        assert_eq!(
+            filter.counters(),
+            FilterCounters {
+                discarded: 1,
+                resets: 0
+            }
+        );
+    }
+
+    #[test]
+    fn outliers_are_kept_until_the_window_holds_enough_points() {
+        let mut filter = HostTimeFilter::with_config(threshold(4));
+        feed(&mut filter, 0, 0, MIN_POINTS_FOR_REJECTION as u64 - 1);
+        let samples = 15 * 512;
+        filter.sample_time_to_host_time(host_of(samples) + 5_000, samples);
+        assert_eq!(filter.last_event(), FilterEvent::Accepted);
+    }
+
+    #[test]
+    fn filter_resets_after_reset_after_outliers_in_a_row() {
+        let mut filter = HostTimeFilter::with_config(threshold(3));
+        feed(&mut filter, 0, 0, 32);
+        let late = |buffer: u64| host_of(buffer * 512) + 5_000;
+        let events: Vec<FilterEvent> = (32..35)
+            .map(|buffer| {
+                filter.sample_time_to_host_time(late(buffer), buffer * 512);
+                filter.last_event()
+            })
+            .collect();
+        assert_eq!(
+            events,
+            [
+                FilterEvent::Discarded,
+                FilterEvent::Discarded,
+                FilterEvent::Reset
+            ]
+        );
+        // The filter starts over with the new timing.
+        assert_eq!(filter.diagnostics().points, 1);
+        assert_eq!(
+            filter.sample_time_to_host_time(late(35), 35 * 512),
+            late(35)
+        );
+        assert_eq!(
+            filter.counters(),
+            FilterCounters {
+                discarded: 2,
+                resets: 1
+            }
+        );
+    }
+
+    #[test]
+    fn backwards_sample_clock_resets_the_filter() {
+        for config in [HostTimeFilterConfig::default(), threshold(4)] {
+            let mut filter = HostTimeFilter::with_config(config);
+            feed(&mut filter, 1_000_000, 0, 32);
+            // The device restarted, and counts from zero again.
+            assert_eq!(filter.sample_time_to_host_time(500_000, 0), 500_000);
+            assert_eq!(filter.last_event(), FilterEvent::Reset);
+            assert_eq!(filter.diagnostics().points, 1);
+            assert_eq!(filter.counters().resets, 1);
+        }
+    }
+
+    #[test]
+    fn counters_survive_reset() {
+        let mut filter = HostTimeFilter::with_config(threshold(4));
+        feed(&mut filter, 0, 0, 32);
+        filter.sample_time_to_host_time(host_of(32 * 512) + 5_000, 32 * 512);
+        filter.sample_time_to_host_time(0, 0);
+        filter.reset();
+        assert_eq!(
+            filter.counters(),
+            FilterCounters {
+                discarded: 1,
+                resets: 1
+            }
+        );
+    }
+
+    #[test]
+    fn diagnostics_of_an_empty_filter() {
+        let diagnostics = HostTimeFilter::new().diagnostics();
+        assert_eq!(diagnostics.fitted_sample_rate, None);
+        assert_eq!(diagnostics.points, 0);
+        assert_eq!(diagnostics.fill, 0.);
+        assert_eq!(diagnostics.span, Duration::ZERO);
+        assert!(!diagnostics.is_reliable);
+    }
+
+    #[test]
+    fn diagnostics_measure_rate_jitter_and_span() {
+        let mut filter = HostTimeFilter::new();
+        // A 48 kHz device, which runs 100 ppm fast, with ±50 µs of alternating jitter.
+        let micros_per_sample = 1e6 / (48_000. * (1. + 100e-6));
+        for i in 0..128u64 {
+            let jitter = if i.is_multiple_of(2) { 50. } else { -50. };
+            let host = 1e9 + (i * 480) as f64 * micros_per_sample + jitter;
+            filter.sample_time_to_host_time(host.round() as i64, i * 480);
+        }
+
+        let diagnostics = filter.diagnostics();
+        let rate = diagnostics.fitted_sample_rate.unwrap();
+        assert!((rate - 48_004.8).abs() < 0.5, "{rate}");
+        let drift = diagnostics.drift_ppm(48_000.).unwrap();
+        assert!((drift - 100.).abs() < 10., "{drift}");
+        assert!(
+            (diagnostics.rms_jitter_micros - 50.).abs() < 1.,
+            "{}",
+            diagnostics.rms_jitter_micros
+        );
+        assert_eq!(diagnostics.points, 128);
+        assert_eq!(diagnostics.fill, 0.25);
+        // 127 buffers of 10 ms, with the jitter of the first and last point.
+        let span = diagnostics.span.as_micros() as i64;
+        assert!((span - 1_269_873).abs() <= 101, "{span}");
+        assert!(diagnostics.is_reliable);
+    }
+
+    #[test]
+    fn fitted_sample_rate_needs_two_sample_times() {
+        let mut filter = HostTimeFilter::new();
+        filter.sample_time_to_host_time(1_000, 0);
+        assert_eq!(filter.diagnostics().fitted_sample_rate, None);
+        filter.sample_time_to_host_time(2_000, 0);
+        assert_eq!(filter.diagnostics().fitted_sample_rate, None);
+        filter.sample_time_to_host_time(3_000, 48);
+        assert!(filter.diagnostics().fitted_sample_rate.is_some());
+    }
+
+    #[test]
+    fn reliable_once_the_window_is_filled_enough() {
+        let mut filter = HostTimeFilter::new();
+        feed(&mut filter, 0, 0, 127);
+        assert!(!filter.diagnostics().is_reliable);
+        feed(&mut filter, 127 * 512, host_of(127 * 512), 1);
+        assert!(filter.diagnostics().is_reliable);
+    }
+
+    #[test]
+    fn small_windows_become_reliable_once_full() {
+        for window in [1, 4, 15] {
+            let mut filter = HostTimeFilter::with_config(HostTimeFilterConfig {
+                window: FilterWindow::Points(window),
+                ..Default::default()
+            });
+            let last = (window as u64 - 1) * 512;
+            feed(&mut filter, 0, 0, window as u64 - 1);
+            assert!(!filter.diagnostics().is_reliable, "window {window}");
+            feed(&mut filter, last, host_of(last), 1);
+            assert_eq!(filter.counters().resets, 0);
+            let diagnostics = filter.diagnostics();
+            assert_eq!(diagnostics.fill, 1.);
+            assert!(diagnostics.is_reliable, "window {window}");
+        }
+    }
+
+    #[test]
+    fn huge_clock_jumps_reset_the_filter() {
+        let mut filter = HostTimeFilter::new();
+        feed(&mut filter, 0, 1_000_000, 64);
+        assert_eq!(filter.counters().resets, 0);
+
+        // The sums of a jump this far would overflow.
+        assert_eq!(
+            filter.sample_time_to_host_time(3_000_000, u64::MAX / 2),
+            3_000_000
+        );
+        assert_eq!(filter.last_event(), FilterEvent::Reset);
+        assert_eq!(filter.diagnostics().points, 1);
+        feed(&mut filter, u64::MAX / 2, 3_000_000, 64);
+
+        assert_eq!(
+            filter.sample_time_to_host_time(i64::MAX / 2, u64::MAX / 2),
+            i64::MAX / 2
+        );
+        assert_eq!(filter.last_event(), FilterEvent::Reset);
+        feed(&mut filter, u64::MAX / 2, i64::MAX / 2, 64);
+        assert_eq!(filter.counters().resets, 2);
+        assert_eq!(filter.last_event(), FilterEvent::Accepted);
+    }
+
+    #[test]
+    fn large_clock_jumps_within_the_window_are_kept() {
+        let mut filter = HostTimeFilter::new();
+        feed(&mut filter, 0, 0, 64);
+        // A day later, which fits into the sums of the default window easily.
+        let day = 86_400_000_000;
+        filter.sample_time_to_host_time(day, 48_000 * 86_400);
+        assert_eq!(filter.last_event(), FilterEvent::Accepted);
+        assert_eq!(filter.counters().resets, 0);
     }
 }
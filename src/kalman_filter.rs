@@ -0,0 +1,158 @@
+use crate::HostTimeEstimator;
+
+const DEFAULT_JITTER_MICROS: f64 = 300.;
+const DEFAULT_LATENCY_NOISE_MICROS: f64 = 50.;
+const DEFAULT_RATE_NOISE_PPM: f64 = 1.;
+// How far off the actual sample rate may be from the nominal one at the start.
+const INITIAL_RATE_ERROR_PPM: f64 = 1000.;
+
+/// A Kalman filter, which estimates the host time of the sample clock and the duration
+/// of a sample.
+///
+/// The filter weighs each new host time by how uncertain its own prediction is compared
+/// to the expected jitter of the callbacks. Unlike the
+/// [DelayLockedLoop](crate::DelayLockedLoop), which uses the same gain all the time, it
+/// trusts the first points more and settles on a lower gain later. How quickly it follows
+/// changes of the latency and the sample rate is set with the expected noise of both.
+#[derive(Clone, Copy, Debug)]
+pub struct KalmanFilter {
+    sample_rate: f64,
+    // Variance of the host time of a callback in µs²
+    measurement_variance: f64,
+    // Growth of the variance of the latency per second in µs²
+    latency_variance: f64,
+    // Growth of the variance of the sample duration per second in (µs / sample)²
+    rate_variance: f64,
+    // State after the last callback, or None before the first one
+    state: Option<FilterState>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct FilterState {
+    sample_clock: u64,
+    // Filtered host time of `sample_clock` in micros
+    time: f64,
+    // Filtered duration of one sample in micros
+    period: f64,
+    // Covariance of time and period
+    covariance: [[f64; 2]; 2],
+}
+
+impl KalmanFilter {
+    /// Create a filter for the given nominal sample rate in Hz, which expects 300 µs of
+    /// jitter, a latency that wanders by 50 µs and a sample rate that wanders by 1 ppm
+    /// per square root of a second.
+    pub fn new(sample_rate: f64) -> Self {
+        Self::with_noise(
+            sample_rate,
+            DEFAULT_JITTER_MICROS,
+            DEFAULT_LATENCY_NOISE_MICROS,
+            DEFAULT_RATE_NOISE_PPM,
+        )
+    }
+
+    /// Create a filter for the given nominal sample rate in Hz, the standard deviation
+    /// of the host time of the callbacks in µs, and how much the latency in µs and the
+    /// sample rate in ppm wander per square root of a second.
+    ///
+    /// Panics, if the sample rate and jitter are not finite and positive, or the rate
+    /// noise is not finite and non-negative.
+    pub fn with_noise(
+        sample_rate: f64,
+        jitter_micros: f64,
+        latency_noise_micros: f64,
+        rate_noise_ppm: f64,
+    ) -> Self {
+        assert!(
+            sample_rate.is_finite() && sample_rate > 0.,
+            "Sample rate must be finite and positive."
+        );
+        assert!(
+            jitter_micros.is_finite() && jitter_micros > 0.,
+            "Jitter must be finite and positive."
+        );
+        assert!(
+            latency_noise_micros.is_finite() && latency_noise_micros >= 0.,
+            "Latency noise must be finite and non-negative."
+        );
+        assert!(
+            rate_noise_ppm.is_finite() && rate_noise_ppm >= 0.,
+            "Rate noise must be finite and non-negative."
+        );
+        let period = 1e6 / sample_rate;
+        Self {
+            sample_rate,
+            measurement_variance: jitter_micros * jitter_micros,
+            latency_variance: latency_noise_micros * latency_noise_micros,
+            rate_variance: (rate_noise_ppm * 1e-6 * period).powi(2),
+            state: None,
+        }
+    }
+
+    /// Forget the state of the filter. The next callback starts it again.
+    pub fn reset(&mut self) {
+        self.state = None;
+    }
+
+    /// The filtered host time in microseconds of the given sample clock.
+    pub fn sample_time_to_host_time(&mut self, clock_micros: i64, sample_clock: u64) -> i64 {
+        let state = match self.state {
+            // A sample clock that went backwards means the device was restarted
+            Some(state) if sample_clock >= state.sample_clock => state,
+            _ => {
+                let period = 1e6 / self.sample_rate;
+                self.state = Some(FilterState {
+                    sample_clock,
+                    time: clock_micros as f64,
+                    period,
+                    covariance: [
+                        [self.measurement_variance, 0.],
+                        [0., (INITIAL_RATE_ERROR_PPM * 1e-6 * period).powi(2)],
+                    ],
+                });
+                return clock_micros;
+            }
+        };
+        if sample_clock == state.sample_clock {
+            return state.time.round() as i64;
+        }
+
+        // Predict the state at the new sample clock
+        let samples = (sample_clock - state.sample_clock) as f64;
+        let [[p00, p01], [_, p11]] = state.covariance;
+        let seconds = samples / self.sample_rate;
+        let p00 = p00 + 2. * samples * p01 + samples * samples * p11;
+        let p01 = p01 + samples * p11;
+        let p00 = p00 + self.latency_variance * seconds;
+        let p11 = p11 + self.rate_variance * seconds;
+        let predicted = state.time + state.period * samples;
+
+        // Correct it with the measured host time
+        let error = clock_micros as f64 - predicted;
+        let innovation_variance = p00 + self.measurement_variance;
+        let time_gain = p00 / innovation_variance;
+        let period_gain = p01 / innovation_variance;
+
+        let time = predicted + time_gain * error;
+        self.state = Some(FilterState {
+            sample_clock,
+            time,
+            period: state.period + period_gain * error,
+            covariance: [
+                [(1. - time_gain) * p00, (1. - time_gain) * p01],
+                [(1. - time_gain) * p01, p11 - period_gain * p01],
+            ],
+        });
+        time.round() as i64
+    }
+}
+
+impl HostTimeEstimator for KalmanFilter {
+    fn sample_time_to_host_time(&mut self, clock_micros: i64, sample_clock: u64) -> i64 {
+        KalmanFilter::sample_time_to_host_time(self, clock_micros, sample_clock)
+    }
+
+    fn reset(&mut self) {
+        KalmanFilter::reset(self)
+    }
+}
@@ -16,11 +16,55 @@ mod rust_bindings {
 }
 
 mod abl_link;
+mod backend;
+mod builder;
+mod clock;
+mod clock_mapping;
+mod delay_locked_loop;
+mod error;
+#[cfg(feature = "async")]
+mod event_stream;
+mod events;
+mod handles;
+mod host_time_estimator;
 mod host_time_filter;
+mod kalman_filter;
+#[cfg(feature = "rt-check")]
+mod rt_check;
+mod session_snapshot;
 mod session_state;
+mod simulated;
 mod split;
+mod timeline;
+mod units;
 
 // PUBLIC API
-pub use abl_link::AblLink;
-pub use host_time_filter::HostTimeFilter;
+pub use abl_link::{AblLink, CallbackId, PanicPolicy, Subscription};
+pub use backend::{LinkBackend, SessionStateOps};
+pub use builder::AblLinkBuilder;
+pub use clock::{Clock, InstantClock, ManualClock};
+pub use clock_mapping::ClockMapping;
+#[cfg(unix)]
+pub use clock_mapping::Timespec;
+pub use delay_locked_loop::DelayLockedLoop;
+pub use error::{Error, MAX_TEMPO, MIN_TEMPO};
+#[cfg(feature = "async")]
+pub use event_stream::LinkEventStream;
+pub use events::{Capacity, Delivery, EventsConfig, LinkEvent, LinkEvents};
+// The channel behind LinkEvents, so that its receiver can be used with the same version
+pub use crossbeam_channel;
+pub use handles::{AppHandle, AudioThreadHandle, WeakAppHandle};
+pub use host_time_estimator::HostTimeEstimator;
+pub use host_time_filter::{
+    FilterCounters, FilterDiagnostics, FilterEvent, FilterSlope, FilterWindow, HostTimeFilter,
+    HostTimeFilterConfig, OutlierRejection, WarmUp,
+};
+pub use kalman_filter::KalmanFilter;
+pub use session_snapshot::SessionSnapshot;
 pub use session_state::SessionState;
+pub use simulated::{SimulatedLink, SimulatedSession, SimulatedSessionState, SimulationConfig};
+pub use timeline::Timeline;
+pub use units::{Beats, Bpm, Micros, Quantum};
+
+#[cfg(feature = "test-util")]
+pub mod test_util;
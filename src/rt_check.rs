@@ -0,0 +1,192 @@
+//! Runtime checks of the thread and realtime safety rules, which are only documented
+//! in the doc comments otherwise. Enabled with the `rt-check` feature.
+//!
+//! A violation panics in debug builds and is printed to stderr in release builds.
+
+use std::{
+    cell::OnceCell,
+    fmt,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+    thread::{self, ThreadId},
+};
+
+thread_local! {
+    // The number of AudioThreadHandles, which use this thread as their audio thread. Shared
+    // with the handles, so they can unregister the thread when they are dropped elsewhere.
+    static AUDIO_THREAD_HANDLES: Arc<AtomicUsize> = Arc::new(AtomicUsize::new(0));
+}
+
+fn report(message: fmt::Arguments) {
+    if cfg!(debug_assertions) {
+        panic!("rusty_link rt-check: {message}");
+    } else {
+        eprintln!("rusty_link rt-check: {message}");
+    }
+}
+
+/// The thread an [AudioThreadHandle](crate::AudioThreadHandle) is used on. Recorded by
+/// the first `capture_audio_session_state` call, and unregistered when the handle is dropped.
+#[derive(Default)]
+pub(crate) struct AudioThread {
+    registered: OnceCell<(ThreadId, Arc<AtomicUsize>)>,
+}
+
+impl Drop for AudioThread {
+    fn drop(&mut self) {
+        if let Some((_, handles)) = self.registered.get() {
+            handles.fetch_sub(1, Ordering::Relaxed);
+        }
+    }
+}
+
+impl AudioThread {
+    /// Registers the current thread as the audio thread on the first call, and reports
+    /// calls from any other thread afterwards.
+    pub(crate) fn register(&self, function: &str) {
+        let current = thread::current().id();
+        match self.registered.get() {
+            None => {
+                let handles = AUDIO_THREAD_HANDLES.with(|handles| {
+                    handles.fetch_add(1, Ordering::Relaxed);
+                    Arc::clone(handles)
+                });
+                let _ = self.registered.set((current, handles));
+            }
+            Some(&(id, _)) if id != current => report(format_args!(
+                "{function} called from {current:?}, but the audio thread is {id:?}"
+            )),
+            Some(_) => {}
+        }
+    }
+
+    /// Reports calls from any other thread than the registered audio thread.
+    pub(crate) fn check(&self, function: &str) {
+        let current = thread::current().id();
+        if let Some(&(id, _)) = self.registered.get()
+            && id != current
+        {
+            report(format_args!(
+                "{function} called from {current:?}, but the audio thread is {id:?}"
+            ));
+        }
+    }
+}
+
+/// Reports calls of functions, which are not realtime-safe, from an audio thread.
+pub(crate) fn not_on_audio_thread(function: &str) {
+    if is_audio_thread() {
+        report(format_args!(
+            "{function} is not realtime-safe, but was called from the audio thread"
+        ));
+    }
+}
+
+// True, while an AudioThreadHandle uses the current thread as its audio thread.
+fn is_audio_thread() -> bool {
+    AUDIO_THREAD_HANDLES.with(|handles| handles.load(Ordering::Relaxed) > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{AblLink, AudioThreadHandle, SessionState};
+    use std::{
+        panic::{self, AssertUnwindSafe},
+        sync::mpsc,
+    };
+
+    // The message of the violation reported by `f`, which panics in debug builds.
+    fn violation(f: impl FnOnce()) -> String {
+        let payload = panic::catch_unwind(AssertUnwindSafe(f)).expect_err("no violation reported");
+        match payload.downcast::<String>() {
+            Ok(message) => *message,
+            Err(_) => panic!("unexpected panic payload"),
+        }
+    }
+
+    // A handle, which registered the current thread as its audio thread.
+    fn registered_handle(link: &AblLink, session_state: &mut SessionState) -> AudioThreadHandle {
+        let handle = link.audio_thread_handle().unwrap();
+        handle.capture_audio_session_state(session_state);
+        handle
+    }
+
+    #[test]
+    fn dropping_the_handle_unregisters_the_audio_thread() {
+        let link = AblLink::new(120.);
+        let (handle_sender, handle_receiver) = mpsc::channel();
+        let (dropped_sender, dropped_receiver) = mpsc::channel();
+
+        let audio_thread = thread::spawn(move || {
+            let mut session_state = SessionState::new();
+            let handle = link.audio_thread_handle().unwrap();
+            handle.capture_audio_session_state(&mut session_state);
+            assert!(is_audio_thread());
+
+            // Dropped on another thread.
+            handle_sender.send(handle).unwrap();
+            dropped_receiver.recv().unwrap();
+            assert!(!is_audio_thread());
+            SessionState::new();
+        });
+
+        drop(handle_receiver.recv().unwrap());
+        assert!(!is_audio_thread());
+        dropped_sender.send(()).unwrap();
+        audio_thread.join().unwrap();
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn capture_from_a_second_thread_panics() {
+        let link = AblLink::new(120.);
+        let mut session_state = SessionState::new();
+        let handle = registered_handle(&link, &mut session_state);
+
+        let message = thread::spawn(move || {
+            violation(|| handle.capture_audio_session_state(&mut session_state))
+        })
+        .join()
+        .unwrap();
+        assert!(message.contains("capture_audio_session_state called from"));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn commit_off_the_audio_thread_panics() {
+        let link = AblLink::new(120.);
+        let mut session_state = SessionState::new();
+        let handle = registered_handle(&link, &mut session_state);
+
+        let message =
+            thread::spawn(move || violation(|| handle.commit_audio_session_state(&session_state)))
+                .join()
+                .unwrap();
+        assert!(message.contains("commit_audio_session_state called from"));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn enable_on_the_audio_thread_panics() {
+        let link = AblLink::new(120.);
+        let mut session_state = SessionState::new();
+        let _handle = registered_handle(&link, &mut session_state);
+
+        let message = violation(|| link.enable(true));
+        assert!(message.contains("AblLink::enable is not realtime-safe"));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn session_state_new_on_the_audio_thread_panics() {
+        let link = AblLink::new(120.);
+        let mut session_state = SessionState::new();
+        let _handle = registered_handle(&link, &mut session_state);
+
+        let message = violation(|| drop(SessionState::new()));
+        assert!(message.contains("SessionState::new is not realtime-safe"));
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{Beats, Bpm, Micros, Quantum, SessionState, Timeline, rust_bindings::*, timeline};
+
+// A quantum large enough, that the closest phase match of any realistic beat origin is the
+// beat origin itself. Still small enough to be exact in f64 microbeats.
+const ORIGIN_QUANTUM: f64 = (1u64 << 31) as f64;
+
+/// A copy of the values of a [SessionState], created by [SessionState::snapshot].
+///
+/// Unlike a SessionState, a snapshot is a plain Rust value. It can be copied, compared,
+/// printed and sent to other threads, and it answers `beat_at_time`, `phase_at_time` and
+/// `time_at_beat` without calling into abl_link, with the same results as the
+/// SessionState it was taken from.
+///
+/// With the `serde` feature, SessionSnapshot implements `Serialize` and `Deserialize`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SessionSnapshot {
+    timeline: Timeline,
+    is_playing: bool,
+    time_for_is_playing: i64,
+}
+
+impl SessionState {
+    /// Take a [SessionSnapshot] of this session state.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: yes
+    pub fn snapshot(&self) -> SessionSnapshot {
+        // abl_link does not expose the beat and time origin of the timeline, but the phase
+        // encoding reveals them: With a huge quantum, beat_at_time returns the beat with the
+        // phase of (beat - beat_origin), which is closest to the beat.
+        let (beat_origin, time_origin) = unsafe {
+            let time = abl_link_time_at_beat(self.session_state, 0., 0.);
+            let beat = abl_link_beat_at_time(self.session_state, time, 0.);
+            let beat_in_phase = abl_link_beat_at_time(self.session_state, time, ORIGIN_QUANTUM);
+            let beat_origin =
+                timeline::to_micro_beats(beat) - timeline::to_micro_beats(beat_in_phase);
+            let time_origin =
+                abl_link_time_at_beat(self.session_state, timeline::to_beats(beat_origin), 0.);
+            (beat_origin, time_origin)
+        };
+
+        SessionSnapshot {
+            timeline: Timeline {
+                tempo: self.tempo(),
+                beat_origin,
+                time_origin,
+            },
+            is_playing: self.is_playing(),
+            time_for_is_playing: self.time_for_is_playing(),
+        }
+    }
+}
+
+impl SessionSnapshot {
+    /// The beat/time mapping of the session state, which can be used to plan changes of
+    /// the timeline without a SessionState.
+    pub fn timeline(&self) -> Timeline {
+        self.timeline
+    }
+
+    /// The tempo of the timeline, in Beats Per Minute. See [SessionState::tempo].
+    pub fn tempo(&self) -> f64 {
+        self.timeline.tempo()
+    }
+
+    /// The beat at the time origin of the timeline.
+    pub fn beat_origin(&self) -> f64 {
+        self.timeline.beat_origin()
+    }
+
+    /// The time in microseconds, at which the timeline is at its beat origin.
+    pub fn time_origin(&self) -> i64 {
+        self.timeline.time_origin()
+    }
+
+    /// Is transport playing? See [SessionState::is_playing].
+    pub fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    /// Get the time at which a transport start/stop occurs.
+    /// See [SessionState::time_for_is_playing].
+    pub fn time_for_is_playing(&self) -> i64 {
+        self.time_for_is_playing
+    }
+
+    /// Get the beat value corresponding to the given time for the given quantum.
+    /// See [SessionState::beat_at_time].
+    pub fn beat_at_time(&self, time: i64, quantum: f64) -> f64 {
+        self.timeline.beat_at_time(time, quantum)
+    }
+
+    /// Get the session phase at the given time for the given quantum.
+    /// See [SessionState::phase_at_time].
+    pub fn phase_at_time(&self, time: i64, quantum: f64) -> f64 {
+        self.timeline.phase_at_time(time, quantum)
+    }
+
+    /// Get the time at which the given beat occurs for the given quantum.
+    /// See [SessionState::time_at_beat].
+    pub fn time_at_beat(&self, beat: f64, quantum: f64) -> i64 {
+        self.timeline.time_at_beat(beat, quantum)
+    }
+
+    // Typed variants of the functions above, which can not be called with invalid values.
+
+    /// The tempo of the timeline. See [SessionSnapshot::tempo].
+    pub fn tempo_bpm(&self) -> Bpm {
+        self.timeline.tempo_bpm()
+    }
+
+    /// Get the time at which a transport start/stop occurs.
+    /// See [SessionSnapshot::time_for_is_playing].
+    pub fn time_for_is_playing_micros(&self) -> Micros {
+        Micros::new(self.time_for_is_playing)
+    }
+
+    /// Get the beat value corresponding to the given time for the given quantum.
+    /// See [SessionSnapshot::beat_at_time].
+    pub fn beat_at(&self, time: Micros, quantum: Quantum) -> Beats {
+        self.timeline.beat_at(time, quantum)
+    }
+
+    /// Get the session phase at the given time for the given quantum.
+    /// See [SessionSnapshot::phase_at_time].
+    pub fn phase_at(&self, time: Micros, quantum: Quantum) -> Beats {
+        self.timeline.phase_at(time, quantum)
+    }
+
+    /// Get the time at which the given beat occurs for the given quantum.
+    /// See [SessionSnapshot::time_at_beat].
+    pub fn time_at(&self, beat: Beats, quantum: Quantum) -> Micros {
+        self.timeline.time_at(beat, quantum)
+    }
+}
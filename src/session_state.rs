@@ -1,4 +1,6 @@
-use crate::rust_bindings::*;
+#[cfg(feature = "rt-check")]
+use crate::rt_check;
+use crate::{Beats, Bpm, Error, Micros, Quantum, error, rust_bindings::*};
 
 ///  The representation of the current local state of a client in a Link Session.
 ///
@@ -43,12 +45,29 @@ impl SessionState {
     ///  The session_state is to be used with the abl_link_capture... and
     ///  abl_link_commit... functions to capture snapshots of the current link state and pass
     ///  changes to the link session.
+    ///
+    ///  Panics, if abl_link fails to create the session state. See [SessionState::try_new]
+    ///  for a fallible version.
     pub fn new() -> SessionState {
-        unsafe {
-            SessionState {
-                session_state: abl_link_create_session_state(),
-            }
+        Self::try_new().expect("Failed to create abl_link session state.")
+    }
+
+    /// Create a new SessionState instance.
+    ///
+    ///  Thread-safe: yes
+    ///
+    ///  Realtime-safe: no
+    ///
+    ///  Returns [Error::SessionStateCreationFailed], if abl_link fails to create the
+    ///  session state.
+    pub fn try_new() -> Result<SessionState, Error> {
+        #[cfg(feature = "rt-check")]
+        rt_check::not_on_audio_thread("SessionState::new");
+        let session_state = unsafe { abl_link_create_session_state() };
+        if session_state.impl_.is_null() {
+            return Err(Error::SessionStateCreationFailed);
         }
+        Ok(SessionState { session_state })
     }
 
     /// The tempo of the timeline, in Beats Per Minute.
@@ -178,4 +197,305 @@ impl SessionState {
             )
         }
     }
+
+    // Variants of the functions above, which reject invalid values before they reach
+    // abl_link. Tempos have to be finite and in the range Link supports, beats have to be
+    // finite, and quanta have to be finite and not negative.
+
+    /// Set the timeline tempo. See [SessionState::set_tempo].
+    ///
+    ///  Returns [Error::InvalidTempo] and leaves the timeline unchanged, if the tempo is
+    ///  not finite or outside of the range Link supports.
+    pub fn try_set_tempo(&mut self, bpm: f64, at_time: i64) -> Result<(), Error> {
+        self.set_tempo(error::validate_tempo(bpm)?, at_time);
+        Ok(())
+    }
+
+    /// Get the beat value corresponding to the given time for the given quantum.
+    /// See [SessionState::beat_at_time].
+    ///
+    ///  Returns [Error::InvalidQuantum], if the quantum is not finite or negative.
+    pub fn try_beat_at_time(&self, time: i64, quantum: f64) -> Result<f64, Error> {
+        Ok(self.beat_at_time(time, error::validate_quantum(quantum)?))
+    }
+
+    /// Get the session phase at the given time for the given quantum.
+    /// See [SessionState::phase_at_time].
+    ///
+    ///  Returns [Error::InvalidQuantum], if the quantum is not finite or negative.
+    pub fn try_phase_at_time(&self, time: i64, quantum: f64) -> Result<f64, Error> {
+        Ok(self.phase_at_time(time, error::validate_quantum(quantum)?))
+    }
+
+    /// Get the time at which the given beat occurs for the given quantum.
+    /// See [SessionState::time_at_beat].
+    ///
+    ///  Returns [Error::InvalidBeat] or [Error::InvalidQuantum], if the beat is not
+    ///  finite, or the quantum is not finite or negative.
+    pub fn try_time_at_beat(&self, beat: f64, quantum: f64) -> Result<i64, Error> {
+        let beat = error::validate_beat(beat)?;
+        Ok(self.time_at_beat(beat, error::validate_quantum(quantum)?))
+    }
+
+    /// Attempt to map the given beat to the given time in the context of the given quantum.
+    /// See [SessionState::request_beat_at_time].
+    ///
+    ///  Returns [Error::InvalidBeat] or [Error::InvalidQuantum] and leaves the timeline
+    ///  unchanged, if the beat is not finite, or the quantum is not finite or negative.
+    pub fn try_request_beat_at_time(
+        &mut self,
+        beat: f64,
+        time: i64,
+        quantum: f64,
+    ) -> Result<(), Error> {
+        let beat = error::validate_beat(beat)?;
+        self.request_beat_at_time(beat, time, error::validate_quantum(quantum)?);
+        Ok(())
+    }
+
+    /// Rudely re-map the beat/time relationship for all peers in a session.
+    /// See [SessionState::force_beat_at_time].
+    ///
+    ///  Returns [Error::InvalidBeat] or [Error::InvalidQuantum] and leaves the timeline
+    ///  unchanged, if the beat is not finite, or the quantum is not finite or negative.
+    pub fn try_force_beat_at_time(
+        &mut self,
+        beat: f64,
+        time: i64,
+        quantum: f64,
+    ) -> Result<(), Error> {
+        let beat = error::validate_beat(beat)?;
+        self.force_beat_at_time(beat, time, error::validate_quantum(quantum)?);
+        Ok(())
+    }
+
+    /// Attempt to map the given beat to the time when transport is starting to play.
+    /// See [SessionState::request_beat_at_start_playing_time].
+    ///
+    ///  Returns [Error::InvalidBeat] or [Error::InvalidQuantum] and leaves the timeline
+    ///  unchanged, if the beat is not finite, or the quantum is not finite or negative.
+    pub fn try_request_beat_at_start_playing_time(
+        &mut self,
+        beat: f64,
+        quantum: f64,
+    ) -> Result<(), Error> {
+        let beat = error::validate_beat(beat)?;
+        self.request_beat_at_start_playing_time(beat, error::validate_quantum(quantum)?);
+        Ok(())
+    }
+
+    /// Start or stop transport at a given time and attempt to map the given beat to this
+    /// time. See [SessionState::set_is_playing_and_request_beat_at_time].
+    ///
+    ///  Returns [Error::InvalidBeat] or [Error::InvalidQuantum] and leaves the session
+    ///  state unchanged, if the beat is not finite, or the quantum is not finite or negative.
+    pub fn try_set_is_playing_and_request_beat_at_time(
+        &mut self,
+        is_playing: bool,
+        time: i64,
+        beat: f64,
+        quantum: f64,
+    ) -> Result<(), Error> {
+        let beat = error::validate_beat(beat)?;
+        let quantum = error::validate_quantum(quantum)?;
+        self.set_is_playing_and_request_beat_at_time(is_playing, time, beat, quantum);
+        Ok(())
+    }
+
+    // Typed variants of the functions above, which can not be called with invalid values.
+
+    /// The tempo of the timeline. See [SessionState::tempo].
+    pub fn tempo_bpm(&self) -> Bpm {
+        Bpm::new_unchecked(self.tempo())
+    }
+
+    /// Set the timeline tempo, taking effect at the given time. See [SessionState::set_tempo].
+    pub fn set_tempo_bpm(&mut self, bpm: Bpm, at_time: Micros) {
+        unsafe { abl_link_set_tempo(self.session_state, bpm.get(), at_time.get()) }
+    }
+
+    /// Get the beat value corresponding to the given time for the given quantum.
+    /// See [SessionState::beat_at_time].
+    pub fn beat_at(&self, time: Micros, quantum: Quantum) -> Beats {
+        Beats::new_unchecked(unsafe {
+            abl_link_beat_at_time(self.session_state, time.get(), quantum.get())
+        })
+    }
+
+    /// Get the session phase at the given time for the given quantum.
+    /// See [SessionState::phase_at_time].
+    pub fn phase_at(&self, time: Micros, quantum: Quantum) -> Beats {
+        Beats::new_unchecked(unsafe {
+            abl_link_phase_at_time(self.session_state, time.get(), quantum.get())
+        })
+    }
+
+    /// Get the time at which the given beat occurs for the given quantum.
+    /// See [SessionState::time_at_beat].
+    pub fn time_at(&self, beat: Beats, quantum: Quantum) -> Micros {
+        Micros::new(unsafe { abl_link_time_at_beat(self.session_state, beat.get(), quantum.get()) })
+    }
+
+    /// Attempt to map the given beat to the given time in the context of the given quantum.
+    /// See [SessionState::request_beat_at_time].
+    pub fn request_beat_at(&mut self, beat: Beats, time: Micros, quantum: Quantum) {
+        unsafe {
+            abl_link_request_beat_at_time(self.session_state, beat.get(), time.get(), quantum.get())
+        }
+    }
+
+    /// Rudely re-map the beat/time relationship for all peers in a session.
+    /// See [SessionState::force_beat_at_time].
+    pub fn force_beat_at(&mut self, beat: Beats, time: Micros, quantum: Quantum) {
+        unsafe {
+            abl_link_force_beat_at_time(self.session_state, beat.get(), time.get(), quantum.get())
+        }
+    }
+
+    /// Set if transport should be playing or stopped, taking effect at the given time.
+    /// See [SessionState::set_is_playing].
+    pub fn set_is_playing_at(&mut self, is_playing: bool, time: Micros) {
+        unsafe { abl_link_set_is_playing(self.session_state, is_playing, time.get()) }
+    }
+
+    /// Get the time at which a transport start/stop occurs.
+    /// See [SessionState::time_for_is_playing].
+    pub fn time_for_is_playing_micros(&self) -> Micros {
+        Micros::new(unsafe { abl_link_time_for_is_playing(self.session_state) })
+    }
+
+    /// Attempt to map the given beat to the time when transport is starting to play.
+    /// See [SessionState::request_beat_at_start_playing_time].
+    pub fn request_beat_at_start_playing(&mut self, beat: Beats, quantum: Quantum) {
+        unsafe {
+            abl_link_request_beat_at_start_playing_time(
+                self.session_state,
+                beat.get(),
+                quantum.get(),
+            )
+        }
+    }
+
+    /// Start or stop transport at a given time and attempt to map the given beat to this
+    /// time. See [SessionState::set_is_playing_and_request_beat_at_time].
+    pub fn set_is_playing_and_request_beat_at(
+        &mut self,
+        is_playing: bool,
+        time: Micros,
+        beat: Beats,
+        quantum: Quantum,
+    ) {
+        unsafe {
+            abl_link_set_is_playing_and_request_beat_at_time(
+                self.session_state,
+                is_playing,
+                time.get(),
+                beat.get(),
+                quantum.get(),
+            )
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MAX_TEMPO, MIN_TEMPO};
+
+    #[test]
+    fn tempo_bounds_are_accepted() {
+        let mut session_state = SessionState::new();
+        for bpm in [MIN_TEMPO, MAX_TEMPO] {
+            assert_eq!(session_state.try_set_tempo(bpm, 0), Ok(()));
+            assert_eq!(session_state.tempo(), bpm);
+        }
+    }
+
+    #[test]
+    fn invalid_tempo_is_rejected() {
+        let mut session_state = SessionState::new();
+        let before = session_state.snapshot();
+        for bpm in [
+            MIN_TEMPO - 1e-9,
+            MAX_TEMPO + 1e-9,
+            f64::NAN,
+            f64::INFINITY,
+            f64::NEG_INFINITY,
+        ] {
+            let result = session_state.try_set_tempo(bpm, 0);
+            assert!(matches!(result, Err(Error::InvalidTempo(b)) if b.to_bits() == bpm.to_bits()));
+        }
+        assert_eq!(session_state.snapshot(), before);
+    }
+
+    #[test]
+    fn invalid_quantum_is_rejected_by_all_functions() {
+        let mut session_state = SessionState::new();
+        let before = session_state.snapshot();
+        for quantum in [-1., -f64::MIN_POSITIVE, f64::NAN, f64::INFINITY] {
+            let invalid = |result: Result<(), Error>| matches!(result, Err(Error::InvalidQuantum(q)) if q.to_bits() == quantum.to_bits());
+            assert!(invalid(
+                session_state.try_beat_at_time(0, quantum).map(drop)
+            ));
+            assert!(invalid(
+                session_state.try_phase_at_time(0, quantum).map(drop)
+            ));
+            assert!(invalid(
+                session_state.try_time_at_beat(0., quantum).map(drop)
+            ));
+            assert!(invalid(
+                session_state.try_request_beat_at_time(0., 0, quantum)
+            ));
+            assert!(invalid(
+                session_state.try_force_beat_at_time(0., 0, quantum)
+            ));
+            assert!(invalid(
+                session_state.try_request_beat_at_start_playing_time(0., quantum)
+            ));
+            assert!(invalid(
+                session_state.try_set_is_playing_and_request_beat_at_time(true, 0, 0., quantum)
+            ));
+        }
+        assert_eq!(session_state.snapshot(), before);
+    }
+
+    #[test]
+    fn invalid_beat_is_rejected_by_all_functions() {
+        let mut session_state = SessionState::new();
+        let before = session_state.snapshot();
+        for beat in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let invalid = |result: Result<(), Error>| matches!(result, Err(Error::InvalidBeat(b)) if b.to_bits() == beat.to_bits());
+            assert!(invalid(session_state.try_time_at_beat(beat, 4.).map(drop)));
+            assert!(invalid(session_state.try_request_beat_at_time(beat, 0, 4.)));
+            assert!(invalid(session_state.try_force_beat_at_time(beat, 0, 4.)));
+            assert!(invalid(
+                session_state.try_request_beat_at_start_playing_time(beat, 4.)
+            ));
+            assert!(invalid(
+                session_state.try_set_is_playing_and_request_beat_at_time(true, 0, beat, 4.)
+            ));
+        }
+        assert_eq!(session_state.snapshot(), before);
+    }
+
+    #[test]
+    fn valid_values_give_the_results_of_the_unchecked_functions() {
+        let mut session_state = SessionState::new();
+        assert_eq!(
+            session_state.try_force_beat_at_time(8., 1_000_000, 0.),
+            Ok(())
+        );
+        assert_eq!(
+            session_state.try_beat_at_time(3_000_000, 4.),
+            Ok(session_state.beat_at_time(3_000_000, 4.))
+        );
+        assert_eq!(
+            session_state.try_phase_at_time(3_000_000, 4.),
+            Ok(session_state.phase_at_time(3_000_000, 4.))
+        );
+        assert_eq!(
+            session_state.try_time_at_beat(12., 4.),
+            Ok(session_state.time_at_beat(12., 4.))
+        );
+    }
 }
@@ -0,0 +1,573 @@
+use crate::{Clock, Error, LinkBackend, SessionStateOps, Timeline, error};
+use std::{
+    sync::{Arc, Mutex, MutexGuard},
+    time::Duration,
+};
+
+/// How long changes take to reach the other peers of a [SimulatedSession].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SimulationConfig {
+    /// Time from enabling a peer until it has joined the session, and sees and is seen by
+    /// the other peers.
+    pub join_delay: Duration,
+    /// Time from disabling or dropping a peer until the other peers see it leave.
+    pub leave_delay: Duration,
+    /// Time until a committed timeline, that is a new tempo or beat/time mapping, reaches
+    /// the other peers.
+    pub tempo_delay: Duration,
+    /// Time until a committed start/stop state reaches the other peers, which have start
+    /// stop sync enabled.
+    pub start_stop_delay: Duration,
+}
+
+/// A deterministic Link session, which runs in-process on a [Clock] that only moves with
+/// [SimulatedSession::advance].
+///
+/// Peers are added with [SimulatedSession::add_peer] and implement [LinkBackend], so code
+/// which is generic over it can be tested without network discovery and wall-clock time.
+/// Joins, leaves, timelines and start/stop states propagate between the peers with the
+/// delays of the [SimulationConfig]. All peers share the same clock.
+///
+/// Cloning a SimulatedSession returns another handle to the same session.
+#[derive(Clone)]
+pub struct SimulatedSession {
+    world: Arc<Mutex<World>>,
+}
+
+/// A peer of a [SimulatedSession], which implements [LinkBackend].
+///
+/// Dropping the peer makes it leave the session.
+pub struct SimulatedLink {
+    world: Arc<Mutex<World>>,
+    id: usize,
+}
+
+/// The session state of a [SimulatedLink], which implements [SessionStateOps].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimulatedSessionState {
+    timeline: Timeline,
+    is_playing: bool,
+    time_for_is_playing: i64,
+    // Like abl_link, requests are quantized, if other peers were connected at capture time.
+    respect_quantum: bool,
+}
+
+struct World {
+    config: SimulationConfig,
+    now: i64,
+    peers: Vec<Peer>,
+    pending: Vec<Message>,
+    next_seq: u64,
+}
+
+struct Peer {
+    enabled: bool,
+    start_stop_sync: bool,
+    joined: bool,
+    timeline: Timeline,
+    is_playing: bool,
+    time_for_is_playing: i64,
+}
+
+enum Update {
+    Join,
+    Leave,
+    Timeline(Timeline),
+    StartStop(bool, i64),
+}
+
+struct Message {
+    at: i64,
+    seq: u64,
+    from: usize,
+    update: Update,
+}
+
+fn micros(duration: Duration) -> i64 {
+    i64::try_from(duration.as_micros()).expect("simulation delay out of range")
+}
+
+impl World {
+    fn send(&mut self, from: usize, delay: Duration, update: Update) {
+        self.pending.push(Message {
+            at: self.now + micros(delay),
+            seq: self.next_seq,
+            from,
+            update,
+        });
+        self.next_seq += 1;
+        self.deliver_due();
+    }
+
+    /// Delivers all messages, which are due at the current time, in the order they
+    /// were sent.
+    fn deliver_due(&mut self) {
+        loop {
+            let Some(index) = self
+                .pending
+                .iter()
+                .enumerate()
+                .filter(|(_, message)| message.at <= self.now)
+                .min_by_key(|(_, message)| (message.at, message.seq))
+                .map(|(index, _)| index)
+            else {
+                return;
+            };
+            let message = self.pending.swap_remove(index);
+            self.deliver(message);
+        }
+    }
+
+    fn deliver(&mut self, message: Message) {
+        let from = message.from;
+        match message.update {
+            Update::Join => {
+                if !self.peers[from].enabled {
+                    return;
+                }
+                // Adopt the timeline and start/stop state of the session that is joined.
+                // A peer, which was enabled again before its Leave was delivered, is still
+                // joined, but has missed the updates while it was disabled.
+                if let Some(other) = (0..self.peers.len())
+                    .find(|&id| id != from && self.peers[id].enabled && self.peers[id].joined)
+                {
+                    self.peers[from].timeline = self.peers[other].timeline;
+                    if self.peers[from].start_stop_sync && self.peers[other].start_stop_sync {
+                        self.peers[from].is_playing = self.peers[other].is_playing;
+                        self.peers[from].time_for_is_playing =
+                            self.peers[other].time_for_is_playing;
+                    }
+                }
+                self.peers[from].joined = true;
+            }
+            Update::Leave => {
+                if !self.peers[from].enabled {
+                    self.peers[from].joined = false;
+                }
+            }
+            Update::Timeline(timeline) => {
+                for peer in self.receivers(from) {
+                    peer.timeline = timeline;
+                }
+            }
+            Update::StartStop(is_playing, time) => {
+                for peer in self.receivers(from).filter(|peer| peer.start_stop_sync) {
+                    peer.is_playing = is_playing;
+                    peer.time_for_is_playing = time;
+                }
+            }
+        }
+    }
+
+    fn receivers(&mut self, from: usize) -> impl Iterator<Item = &mut Peer> {
+        self.peers
+            .iter_mut()
+            .enumerate()
+            .filter(move |(id, peer)| *id != from && peer.enabled && peer.joined)
+            .map(|(_, peer)| peer)
+    }
+
+    fn num_peers(&self, id: usize) -> u64 {
+        let peer = &self.peers[id];
+        if !peer.enabled || !peer.joined {
+            return 0;
+        }
+        self.peers
+            .iter()
+            .enumerate()
+            .filter(|(other, peer)| *other != id && peer.joined)
+            .count() as u64
+    }
+
+    fn is_connected(&self, id: usize) -> bool {
+        self.peers[id].enabled && self.peers[id].joined
+    }
+}
+
+impl SimulatedSession {
+    /// Create an empty session, with its clock at zero.
+    pub fn new(config: SimulationConfig) -> SimulatedSession {
+        SimulatedSession {
+            world: Arc::new(Mutex::new(World {
+                config,
+                now: 0,
+                peers: Vec::new(),
+                pending: Vec::new(),
+                next_seq: 0,
+            })),
+        }
+    }
+
+    /// Add a disabled peer with the given initial tempo, which is at beat zero at the
+    /// current time.
+    ///
+    ///  Returns [Error::InvalidTempo], if the tempo is not finite or outside of the range
+    ///  Link supports.
+    pub fn add_peer(&self, bpm: f64) -> Result<SimulatedLink, Error> {
+        let bpm = error::validate_tempo(bpm)?;
+        let mut world = lock(&self.world);
+        let timeline = Timeline::new(bpm, 0., world.now)?;
+        world.peers.push(Peer {
+            enabled: false,
+            start_stop_sync: false,
+            joined: false,
+            timeline,
+            is_playing: false,
+            time_for_is_playing: 0,
+        });
+        Ok(SimulatedLink {
+            world: Arc::clone(&self.world),
+            id: world.peers.len() - 1,
+        })
+    }
+
+    /// Move the session clock forward, and deliver all changes which are due until then.
+    pub fn advance(&self, duration: Duration) {
+        let mut world = lock(&self.world);
+        let end = world.now + micros(duration);
+        // Step through the due messages, so each is delivered at its own time.
+        while let Some(at) = world
+            .pending
+            .iter()
+            .map(|message| message.at)
+            .filter(|&at| at <= end)
+            .min()
+        {
+            world.now = world.now.max(at);
+            world.deliver_due();
+        }
+        world.now = end;
+    }
+}
+
+fn lock(world: &Mutex<World>) -> MutexGuard<'_, World> {
+    world
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+impl Clock for SimulatedSession {
+    fn clock_micros(&self) -> i64 {
+        lock(&self.world).now
+    }
+}
+
+impl Clock for SimulatedLink {
+    fn clock_micros(&self) -> i64 {
+        lock(&self.world).now
+    }
+}
+
+impl Drop for SimulatedLink {
+    fn drop(&mut self) {
+        self.enable(false);
+    }
+}
+
+impl LinkBackend for SimulatedLink {
+    type SessionState = SimulatedSessionState;
+
+    fn is_enabled(&self) -> bool {
+        lock(&self.world).peers[self.id].enabled
+    }
+
+    fn enable(&self, enable: bool) {
+        let mut world = lock(&self.world);
+        if world.peers[self.id].enabled == enable {
+            return;
+        }
+        world.peers[self.id].enabled = enable;
+        let config = world.config;
+        if enable {
+            world.send(self.id, config.join_delay, Update::Join);
+        } else {
+            world.send(self.id, config.leave_delay, Update::Leave);
+        }
+    }
+
+    fn is_start_stop_sync_enabled(&self) -> bool {
+        lock(&self.world).peers[self.id].start_stop_sync
+    }
+
+    fn enable_start_stop_sync(&self, enable: bool) {
+        lock(&self.world).peers[self.id].start_stop_sync = enable;
+    }
+
+    fn num_peers(&self) -> u64 {
+        lock(&self.world).num_peers(self.id)
+    }
+
+    fn capture_app_session_state(&self, session_state: &mut SimulatedSessionState) {
+        let world = lock(&self.world);
+        let peer = &world.peers[self.id];
+        *session_state = SimulatedSessionState {
+            timeline: peer.timeline,
+            is_playing: peer.is_playing,
+            time_for_is_playing: peer.time_for_is_playing,
+            respect_quantum: world.num_peers(self.id) > 0,
+        };
+    }
+
+    fn commit_app_session_state(&self, session_state: &SimulatedSessionState) {
+        let mut world = lock(&self.world);
+        let config = world.config;
+        let connected = world.is_connected(self.id);
+        let peer = &mut world.peers[self.id];
+
+        let timeline_changed = peer.timeline != session_state.timeline;
+        peer.timeline = session_state.timeline;
+
+        let start_stop_changed = peer.is_playing != session_state.is_playing
+            || peer.time_for_is_playing != session_state.time_for_is_playing;
+        peer.is_playing = session_state.is_playing;
+        peer.time_for_is_playing = session_state.time_for_is_playing;
+        let start_stop_sync = peer.start_stop_sync;
+
+        if connected && timeline_changed {
+            world.send(
+                self.id,
+                config.tempo_delay,
+                Update::Timeline(session_state.timeline),
+            );
+        }
+        if connected && start_stop_changed && start_stop_sync {
+            world.send(
+                self.id,
+                config.start_stop_delay,
+                Update::StartStop(session_state.is_playing, session_state.time_for_is_playing),
+            );
+        }
+    }
+}
+
+impl Default for SimulatedSessionState {
+    fn default() -> Self {
+        SimulatedSessionState {
+            timeline: Timeline {
+                tempo: 120.,
+                beat_origin: 0,
+                time_origin: 0,
+            },
+            is_playing: false,
+            time_for_is_playing: 0,
+            respect_quantum: false,
+        }
+    }
+}
+
+impl SimulatedSessionState {
+    /// The timeline of the session state.
+    pub fn timeline(&self) -> Timeline {
+        self.timeline
+    }
+}
+
+impl SessionStateOps for SimulatedSessionState {
+    fn tempo(&self) -> f64 {
+        self.timeline.tempo()
+    }
+
+    fn set_tempo(&mut self, bpm: f64, at_time: i64) {
+        self.timeline.set_tempo(bpm, at_time)
+    }
+
+    fn beat_at_time(&self, time: i64, quantum: f64) -> f64 {
+        self.timeline.beat_at_time(time, quantum)
+    }
+
+    fn phase_at_time(&self, time: i64, quantum: f64) -> f64 {
+        self.timeline.phase_at_time(time, quantum)
+    }
+
+    fn time_at_beat(&self, beat: f64, quantum: f64) -> i64 {
+        self.timeline.time_at_beat(beat, quantum)
+    }
+
+    fn request_beat_at_time(&mut self, beat: f64, time: i64, quantum: f64) {
+        if self.respect_quantum {
+            self.timeline.request_beat_at_time(beat, time, quantum)
+        } else {
+            self.timeline.force_beat_at_time(beat, time, quantum)
+        }
+    }
+
+    fn force_beat_at_time(&mut self, beat: f64, time: i64, quantum: f64) {
+        self.timeline.force_beat_at_time(beat, time, quantum)
+    }
+
+    fn set_is_playing(&mut self, is_playing: bool, time: i64) {
+        self.is_playing = is_playing;
+        self.time_for_is_playing = time;
+    }
+
+    fn is_playing(&self) -> bool {
+        self.is_playing
+    }
+
+    fn time_for_is_playing(&self) -> i64 {
+        self.time_for_is_playing
+    }
+
+    fn request_beat_at_start_playing_time(&mut self, beat: f64, quantum: f64) {
+        if self.is_playing {
+            self.request_beat_at_time(beat, self.time_for_is_playing, quantum);
+        }
+    }
+
+    fn set_is_playing_and_request_beat_at_time(
+        &mut self,
+        is_playing: bool,
+        time: i64,
+        beat: f64,
+        quantum: f64,
+    ) {
+        self.set_is_playing(is_playing, time);
+        self.request_beat_at_start_playing_time(beat, quantum);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const JOIN: Duration = Duration::from_millis(100);
+    const LEAVE: Duration = Duration::from_millis(200);
+    const TEMPO: Duration = Duration::from_millis(30);
+    const START_STOP: Duration = Duration::from_millis(50);
+    const MICRO: Duration = Duration::from_micros(1);
+
+    fn session() -> SimulatedSession {
+        SimulatedSession::new(SimulationConfig {
+            join_delay: JOIN,
+            leave_delay: LEAVE,
+            tempo_delay: TEMPO,
+            start_stop_delay: START_STOP,
+        })
+    }
+
+    fn capture(link: &SimulatedLink) -> SimulatedSessionState {
+        let mut session_state = SimulatedSessionState::default();
+        link.capture_app_session_state(&mut session_state);
+        session_state
+    }
+
+    // Adds peers, which have joined the session one after the other.
+    fn joined_peers(session: &SimulatedSession, tempos: &[f64]) -> Vec<SimulatedLink> {
+        tempos
+            .iter()
+            .map(|&bpm| {
+                let link = session.add_peer(bpm).unwrap();
+                link.enable_start_stop_sync(true);
+                link.enable(true);
+                session.advance(JOIN);
+                link
+            })
+            .collect()
+    }
+
+    #[test]
+    fn peers_join_after_join_delay_and_adopt_the_session() {
+        let session = session();
+        let a = session.add_peer(120.).unwrap();
+        a.enable_start_stop_sync(true);
+        a.enable(true);
+        session.advance(JOIN);
+        let mut state = capture(&a);
+        state.set_is_playing(true, session.clock_micros());
+        a.commit_app_session_state(&state);
+
+        let b = session.add_peer(90.).unwrap();
+        b.enable_start_stop_sync(true);
+        b.enable(true);
+        session.advance(JOIN - MICRO);
+        assert_eq!((a.num_peers(), b.num_peers()), (0, 0));
+        assert_eq!(capture(&b).tempo(), 90.);
+        assert!(!capture(&b).is_playing());
+
+        session.advance(MICRO);
+        assert_eq!((a.num_peers(), b.num_peers()), (1, 1));
+        assert_eq!(capture(&b).timeline(), capture(&a).timeline());
+        assert!(capture(&b).is_playing());
+        assert_eq!(
+            capture(&b).time_for_is_playing(),
+            capture(&a).time_for_is_playing()
+        );
+    }
+
+    #[test]
+    fn tempo_propagates_after_tempo_delay() {
+        let session = session();
+        let peers = joined_peers(&session, &[120., 120.]);
+        let mut state = capture(&peers[0]);
+        state.set_tempo(140., session.clock_micros());
+        peers[0].commit_app_session_state(&state);
+
+        session.advance(TEMPO - MICRO);
+        assert_eq!(capture(&peers[1]).tempo(), 120.);
+        session.advance(MICRO);
+        assert_eq!(capture(&peers[1]).timeline(), state.timeline());
+    }
+
+    #[test]
+    fn start_stop_propagates_after_start_stop_delay() {
+        let session = session();
+        let peers = joined_peers(&session, &[120., 120., 120.]);
+        peers[2].enable_start_stop_sync(false);
+        let mut state = capture(&peers[0]);
+        let time = session.clock_micros() + 1_000;
+        state.set_is_playing(true, time);
+        peers[0].commit_app_session_state(&state);
+
+        session.advance(START_STOP - MICRO);
+        assert!(!capture(&peers[1]).is_playing());
+        session.advance(MICRO);
+        assert!(capture(&peers[1]).is_playing());
+        assert_eq!(capture(&peers[1]).time_for_is_playing(), time);
+        // Peers without start/stop sync keep their own state.
+        assert!(!capture(&peers[2]).is_playing());
+    }
+
+    #[test]
+    fn peers_leave_after_leave_delay() {
+        let session = session();
+        let mut peers = joined_peers(&session, &[120., 120., 120.]);
+        assert!(peers.iter().all(|peer| peer.num_peers() == 2));
+
+        peers[0].enable(false);
+        assert_eq!(peers[0].num_peers(), 0);
+        session.advance(LEAVE - MICRO);
+        assert_eq!(peers[1].num_peers(), 2);
+        session.advance(MICRO);
+        assert_eq!(peers[1].num_peers(), 1);
+
+        // Dropping a peer disables it.
+        drop(peers.pop());
+        session.advance(LEAVE - MICRO);
+        assert_eq!(peers[1].num_peers(), 1);
+        session.advance(MICRO);
+        assert_eq!(peers[1].num_peers(), 0);
+    }
+
+    #[test]
+    fn peer_enabled_again_before_its_leave_stays_joined() {
+        let session = session();
+        let peers = joined_peers(&session, &[120., 120.]);
+        peers[1].enable(false);
+        session.advance(Duration::from_millis(10));
+
+        // The disabled peer misses this tempo change.
+        let mut state = capture(&peers[0]);
+        state.set_tempo(150., session.clock_micros());
+        peers[0].commit_app_session_state(&state);
+        session.advance(TEMPO);
+        assert_eq!(capture(&peers[1]).tempo(), 120.);
+
+        peers[1].enable(true);
+        assert_eq!(peers[1].num_peers(), 1);
+        // The Join catches up on the missed tempo change, and the Leave is ignored, as
+        // the peer is enabled again.
+        session.advance(LEAVE);
+        assert_eq!((peers[0].num_peers(), peers[1].num_peers()), (1, 1));
+        assert_eq!(capture(&peers[1]).timeline(), state.timeline());
+    }
+}
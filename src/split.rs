@@ -84,6 +84,19 @@ pub trait Split<Args, Ret> {
     fn trailing_data_trampoline() -> Self::TrailingDataTrampoline;
 }
 
+/// Calls `f` and aborts the process if it panics. The trampolines are called from C code,
+/// so a panic must never unwind out of them. Callers, which can recover from a panic,
+/// catch it themselves before it reaches this point.
+fn call_without_unwinding<R>(f: impl FnOnce() -> R) -> R {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(f)) {
+        Ok(ret) => ret,
+        Err(_) => {
+            eprintln!("rusty_link: a callback called from C code panicked, aborting.");
+            std::process::abort()
+        }
+    }
+}
+
 macro_rules! impl_split {
     ($( $outer:ident ),* ; $( $inner:ident ),*) => {
         impl<Func, Ret, $($outer),*> Split<($( $outer, )*), Ret> for Func
@@ -106,10 +119,8 @@ macro_rules! impl_split {
                 {
                     debug_assert!(!ptr.is_null());
 
-                    unsafe {
-                        let callback: &mut T = &mut *(ptr as *mut T);
-                        callback($($inner),*)
-                    }
+                    let callback: &mut T = unsafe { &mut *(ptr as *mut T) };
+                    call_without_unwinding(|| callback($($inner),*))
                 }
 
                 trampoline::<Func, Ret, $($outer,)*>
@@ -122,10 +133,8 @@ macro_rules! impl_split {
                 {
                     debug_assert!(!ptr.is_null());
 
-                    unsafe {
-                        let callback: &mut T = &mut *(ptr as *mut T);
-                        callback($($inner),*)
-                    }
+                    let callback: &mut T = unsafe { &mut *(ptr as *mut T) };
+                    call_without_unwinding(|| callback($($inner),*))
                 }
 
                 trampoline::<Func, Ret, $($outer,)*>
@@ -149,3 +158,48 @@ impl_split!(A, B, C, D, E, F, G, H, I, K, L; A, B, C, D, E, F, G, H, I, K, L);
 impl_split!(A, B, C, D, E, F, G, H, I, K, L, M; A, B, C, D, E, F, G, H, I, K, L, M);
 impl_split!(A, B, C, D, E, F, G, H, I, K, L, M, N; A, B, C, D, E, F, G, H, I, K, L, M, N);
 impl_split!(A, B, C, D, E, F, G, H, I, K, L, M, N, O; A, B, C, D, E, F, G, H, I, K, L, M, N, O);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, process::Command};
+
+    const CHILD_VAR: &str = "RUSTY_LINK_ABORT_CHILD";
+
+    #[test]
+    fn panic_in_trampoline_aborts() {
+        if env::var_os(CHILD_VAR).is_some() {
+            let mut closure = |_: u64| panic!("test panic");
+            unsafe {
+                let (state, callback) = split_closure_trailing_data::<_, _, ()>(&mut closure);
+                callback(1, state);
+            }
+            unreachable!("the trampoline did not abort");
+        }
+
+        // Run this test again in a child process, which is expected to abort.
+        let status = Command::new(env::current_exe().unwrap())
+            .args(["--exact", "split::tests::panic_in_trampoline_aborts"])
+            .env(CHILD_VAR, "1")
+            .status()
+            .unwrap();
+        assert!(!status.success());
+        #[cfg(unix)]
+        {
+            use std::os::unix::process::ExitStatusExt;
+            assert_eq!(status.signal(), Some(libc::SIGABRT));
+        }
+    }
+
+    #[test]
+    fn trampoline_calls_the_closure() {
+        let mut total = 0;
+        let mut closure = |n: u64| total += n;
+        unsafe {
+            let (state, callback) = split_closure_trailing_data(&mut closure);
+            callback(2, state);
+            callback(3, state);
+        }
+        assert_eq!(total, 5);
+    }
+}
@@ -0,0 +1,404 @@
+//! Helpers for integration tests with several Link peers in one process, and a synthetic
+//! audio device for host time estimators. Enabled with the `test-util` feature.
+//!
+//! [AblLink] peers discover each other through the network interfaces of the machine, like
+//! peers in separate processes would, so these tests need a network interface with
+//! multicast support. A [PeerGroup] of [SimulatedLink] peers runs the same assertions on
+//! a [SimulatedSession], without a network.
+
+use crate::{AblLink, Error, LinkBackend, SessionStateOps, SimulatedLink, SimulatedSession, error};
+use std::{
+    thread,
+    time::{Duration, Instant},
+};
+
+// How often the conditions are checked while waiting.
+const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Polls the condition until it is true, or the timeout has elapsed. Returns the last
+/// result of the condition.
+pub fn wait_until(timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+    let deadline = Instant::now() + timeout;
+    loop {
+        if condition() {
+            return true;
+        }
+        if Instant::now() >= deadline {
+            return false;
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// A group of enabled Link peers in one process, which join the same Link session.
+///
+/// The peers are [AblLink] instances, created with [PeerGroup::new], or [SimulatedLink]s,
+/// created with [PeerGroup::simulated]. The timeouts of a simulated group are measured on
+/// the clock of its [SimulatedSession], which is advanced while waiting.
+///
+/// The assertion functions panic with a description of the failure, like `assert!`.
+pub struct PeerGroup<L: LinkBackend = AblLink> {
+    peers: Vec<L>,
+    session: Option<SimulatedSession>,
+}
+
+impl PeerGroup<AblLink> {
+    /// Create `num_peers` enabled Link instances with the given initial tempo.
+    ///
+    ///  Returns an [Error], if the tempo is invalid, or an instance can not be created.
+    pub fn new(num_peers: usize, bpm: f64) -> Result<PeerGroup<AblLink>, Error> {
+        let peers = (0..num_peers)
+            .map(|_| AblLink::try_new(bpm))
+            .collect::<Result<Vec<_>, _>>()?;
+        for peer in &peers {
+            peer.enable(true);
+        }
+        Ok(PeerGroup {
+            peers,
+            session: None,
+        })
+    }
+}
+
+impl PeerGroup<SimulatedLink> {
+    /// Add `num_peers` enabled peers with the given initial tempo to the session.
+    ///
+    ///  Returns [Error::InvalidTempo], if the tempo is not finite or outside of the range
+    ///  Link supports.
+    pub fn simulated(
+        session: &SimulatedSession,
+        num_peers: usize,
+        bpm: f64,
+    ) -> Result<PeerGroup<SimulatedLink>, Error> {
+        let peers = (0..num_peers)
+            .map(|_| session.add_peer(bpm))
+            .collect::<Result<Vec<_>, _>>()?;
+        for peer in &peers {
+            peer.enable(true);
+        }
+        Ok(PeerGroup {
+            peers,
+            session: Some(session.clone()),
+        })
+    }
+}
+
+impl<L: LinkBackend> PeerGroup<L> {
+    /// All peers of the group.
+    pub fn peers(&self) -> &[L] {
+        &self.peers
+    }
+
+    /// The peer with the given index.
+    pub fn peer(&self, index: usize) -> &L {
+        &self.peers[index]
+    }
+
+    // Like [wait_until], but advances the session instead of sleeping, if the group is
+    // simulated.
+    fn wait_until(&self, timeout: Duration, mut condition: impl FnMut() -> bool) -> bool {
+        let Some(session) = &self.session else {
+            return wait_until(timeout, condition);
+        };
+        let mut waited = Duration::ZERO;
+        loop {
+            if condition() {
+                return true;
+            }
+            if waited >= timeout {
+                return false;
+            }
+            session.advance(POLL_INTERVAL);
+            waited += POLL_INTERVAL;
+        }
+    }
+
+    /// Enable or disable start/stop sync on all peers.
+    pub fn enable_start_stop_sync(&self, enable: bool) {
+        for peer in &self.peers {
+            peer.enable_start_stop_sync(enable);
+        }
+    }
+
+    /// Wait until every peer sees all other peers of the group. Returns false, if they did
+    /// not converge within the timeout.
+    ///
+    /// Other Link peers on the network are counted as well, so this only converges without
+    /// them.
+    pub fn wait_for_peers(&self, timeout: Duration) -> bool {
+        let expected = self.peers.len().saturating_sub(1) as u64;
+        self.wait_until(timeout, || {
+            self.peers.iter().all(|peer| peer.num_peers() == expected)
+        })
+    }
+
+    /// Assert that every peer sees all other peers of the group within the timeout.
+    pub fn assert_peers_converge(&self, timeout: Duration) {
+        if !self.wait_for_peers(timeout) {
+            panic!(
+                "peers did not converge within {timeout:?}, num_peers: {:?}",
+                self.num_peers()
+            );
+        }
+    }
+
+    /// Set the tempo on the peer `from`, and assert that all other peers show it within
+    /// the given time.
+    pub fn assert_tempo_propagates(&self, from: usize, bpm: f64, within: Duration) {
+        let bpm = error::validate_tempo(bpm).expect("invalid tempo");
+        let mut session_state = L::SessionState::default();
+        let link = &self.peers[from];
+        link.capture_app_session_state(&mut session_state);
+        session_state.set_tempo(bpm, link.clock_micros());
+        link.commit_app_session_state(&session_state);
+
+        let propagated = self.wait_until(within, || {
+            self.tempos()
+                .iter()
+                .all(|&tempo| (tempo - bpm).abs() < 1e-6)
+        });
+        if !propagated {
+            panic!(
+                "tempo {bpm} of peer {from} did not propagate within {within:?}, tempos: {:?}",
+                self.tempos()
+            );
+        }
+    }
+
+    /// Start or stop transport on the peer `from`, and assert that all other peers show it
+    /// within the given time. Start/stop sync has to be enabled with
+    /// [PeerGroup::enable_start_stop_sync] for the state to propagate.
+    pub fn assert_start_stop_propagates(&self, from: usize, is_playing: bool, within: Duration) {
+        let mut session_state = L::SessionState::default();
+        let link = &self.peers[from];
+        link.capture_app_session_state(&mut session_state);
+        session_state.set_is_playing(is_playing, link.clock_micros());
+        link.commit_app_session_state(&session_state);
+
+        let propagated = self.wait_until(within, || {
+            self.is_playing()
+                .iter()
+                .all(|&playing| playing == is_playing)
+        });
+        if !propagated {
+            panic!(
+                "is_playing {is_playing} of peer {from} did not propagate within {within:?}, \
+                 is_playing: {:?}",
+                self.is_playing()
+            );
+        }
+    }
+
+    /// Assert that the phases of all peers for the given quantum agree at the current time
+    /// within the given tolerance, in microseconds at the tempo of each peer.
+    pub fn assert_phases_agree(&self, quantum: f64, tolerance_micros: i64) {
+        let offsets = self.phase_offsets_micros(quantum);
+        if let Some((index, offset)) = offsets
+            .iter()
+            .enumerate()
+            .find(|(_, offset)| offset.abs() > tolerance_micros)
+        {
+            panic!(
+                "phase of peer {index} is {offset} µs off from peer 0, more than \
+                 {tolerance_micros} µs, offsets: {offsets:?}"
+            );
+        }
+    }
+
+    /// The phase offset of each peer to the first peer at the same Link clock time, for
+    /// the given quantum, in microseconds.
+    pub fn phase_offsets_micros(&self, quantum: f64) -> Vec<i64> {
+        let Some(first) = self.peers.first() else {
+            return Vec::new();
+        };
+        // All instances in one process share the same clock.
+        let time = first.clock_micros();
+        let mut session_state = L::SessionState::default();
+        let phases: Vec<(f64, f64)> = self
+            .peers
+            .iter()
+            .map(|peer| {
+                peer.capture_app_session_state(&mut session_state);
+                (
+                    session_state.phase_at_time(time, quantum),
+                    session_state.tempo(),
+                )
+            })
+            .collect();
+
+        let reference = phases[0].0;
+        phases
+            .iter()
+            .map(|&(phase, tempo)| {
+                // The shortest distance between the phases, which wrap at the quantum.
+                let mut difference = (phase - reference).rem_euclid(quantum.max(f64::EPSILON));
+                if difference > quantum / 2. {
+                    difference -= quantum;
+                }
+                (difference * 60e6 / tempo).round() as i64
+            })
+            .collect()
+    }
+
+    /// The number of peers each peer sees.
+    pub fn num_peers(&self) -> Vec<u64> {
+        self.peers.iter().map(L::num_peers).collect()
+    }
+
+    /// The session tempo of each peer.
+    pub fn tempos(&self) -> Vec<f64> {
+        let mut session_state = L::SessionState::default();
+        self.peers
+            .iter()
+            .map(|peer| {
+                peer.capture_app_session_state(&mut session_state);
+                session_state.tempo()
+            })
+            .collect()
+    }
+
+    /// The start/stop state of each peer.
+    pub fn is_playing(&self) -> Vec<bool> {
+        let mut session_state = L::SessionState::default();
+        self.peers
+            .iter()
+            .map(|peer| {
+                peer.capture_app_session_state(&mut session_state);
+                session_state.is_playing()
+            })
+            .collect()
+    }
+}
+
+/// The timing of a [SyntheticDevice].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SyntheticDeviceConfig {
+    /// The nominal sample rate in Hz.
+    pub sample_rate: f64,
+    /// The number of samples per callback.
+    pub buffer_size: u64,
+    /// How much faster the sample clock runs than the nominal sample rate, in parts per
+    /// million.
+    pub rate_error_ppm: f64,
+    /// The standard deviation of the random delay of the callbacks in µs.
+    pub jitter_micros: f64,
+    /// The extra delay of every `spike_every`th callback in µs. No callback is delayed, if
+    /// `spike_every` is 0.
+    pub spike_micros: f64,
+    pub spike_every: u64,
+    /// The latency, which is added to the host time of the sample clock after
+    /// `jump_seconds`, in µs.
+    pub jump_micros: f64,
+    pub jump_seconds: f64,
+    /// The host time of the first sample in µs.
+    pub start_micros: f64,
+    /// The seed of the random jitter. The same seed gives the same callbacks.
+    pub seed: u64,
+}
+
+impl Default for SyntheticDeviceConfig {
+    /// A 48 kHz device with 256 sample buffers, which runs 80 ppm fast, with 300 µs of
+    /// jitter, and without spikes or latency jumps.
+    fn default() -> Self {
+        SyntheticDeviceConfig {
+            sample_rate: 48_000.,
+            buffer_size: 256,
+            rate_error_ppm: 80.,
+            jitter_micros: 300.,
+            spike_micros: 0.,
+            spike_every: 0,
+            jump_micros: 0.,
+            jump_seconds: 0.,
+            start_micros: 1e9,
+            seed: 0x2545_f491_4f6c_dd1d,
+        }
+    }
+}
+
+/// One audio callback of a [SyntheticDevice].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DeviceCallback {
+    /// The sample time of the callback.
+    pub sample_clock: u64,
+    /// The time of the sample clock in seconds, at the nominal sample rate.
+    pub seconds: f64,
+    /// The host time at which the callback is called, which is passed to the estimators.
+    pub clock_micros: i64,
+    /// The true host time of the sample clock, which the estimators should find.
+    pub true_micros: f64,
+}
+
+/// An endless, deterministic series of audio callbacks, for testing and comparing
+/// [HostTimeEstimator](crate::HostTimeEstimator)s without an audio device.
+///
+/// The callbacks are called with random jitter, optional late spikes, and a latency,
+/// which jumps once, like USB interfaces sometimes do.
+#[derive(Clone, Debug)]
+pub struct SyntheticDevice {
+    config: SyntheticDeviceConfig,
+    random: Random,
+    callback: u64,
+}
+
+impl SyntheticDevice {
+    pub fn new(config: SyntheticDeviceConfig) -> SyntheticDevice {
+        SyntheticDevice {
+            config,
+            random: Random(config.seed),
+            callback: 0,
+        }
+    }
+
+    /// The configuration of this device.
+    pub fn config(&self) -> &SyntheticDeviceConfig {
+        &self.config
+    }
+}
+
+impl Iterator for SyntheticDevice {
+    type Item = DeviceCallback;
+
+    fn next(&mut self) -> Option<DeviceCallback> {
+        let config = &self.config;
+        let sample_clock = self.callback * config.buffer_size;
+        let seconds = sample_clock as f64 / config.sample_rate;
+        let micros_per_sample = 1e6 / (config.sample_rate * (1. + config.rate_error_ppm * 1e-6));
+        let latency = if seconds >= config.jump_seconds {
+            config.jump_micros
+        } else {
+            0.
+        };
+        let true_micros = config.start_micros + sample_clock as f64 * micros_per_sample + latency;
+
+        let mut clock_micros = true_micros + self.random.next_normal() * config.jitter_micros;
+        if config.spike_every > 0 && self.callback % config.spike_every == config.spike_every - 1 {
+            clock_micros += config.spike_micros;
+        }
+        self.callback += 1;
+
+        Some(DeviceCallback {
+            sample_clock,
+            seconds,
+            clock_micros: clock_micros as i64,
+            true_micros,
+        })
+    }
+}
+
+// Small xorshift generator, so the run is the same every time
+#[derive(Clone, Debug)]
+struct Random(u64);
+
+impl Random {
+    fn next_f64(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    // Roughly normal distributed, with a standard deviation of 1
+    fn next_normal(&mut self) -> f64 {
+        (0..12).map(|_| self.next_f64()).sum::<f64>() - 6.
+    }
+}
@@ -0,0 +1,250 @@
+//! A Rust port of the timeline math of Link (Beats.hpp, Tempo.hpp, Timeline.hpp and
+//! Phase.hpp), which gives the same results as the functions of a
+//! [SessionState](crate::SessionState).
+//!
+//! Link calculates with fixed point beat values in microbeats, so all beats are i64
+//! microbeats here as well, until they are converted back to f64.
+
+use crate::{Beats, Bpm, Error, MAX_TEMPO, MIN_TEMPO, Micros, Quantum, error};
+
+/// Converts beats to microbeats, the same way as Link's `Beats(double)` constructor.
+pub(crate) fn to_micro_beats(beats: f64) -> i64 {
+    (beats * 1e6).round() as i64
+}
+
+/// Converts microbeats to beats, the same way as Link's `Beats::floating`.
+pub(crate) fn to_beats(micro_beats: i64) -> f64 {
+    micro_beats as f64 / 1e6
+}
+
+// Link's Beats modulo operator, which is zero for a zero quantum.
+fn rem(beats: i64, quantum: i64) -> i64 {
+    if quantum == 0 { 0 } else { beats % quantum }
+}
+
+/// Returns a value in the range [0, quantum) corresponding to beats % quantum, except
+/// that negative beat values are handled correctly. Zero, if the quantum is zero.
+fn phase(beats: i64, quantum: i64) -> i64 {
+    if quantum == 0 {
+        return 0;
+    }
+    // Handle negative beat values by doing the computation relative to an origin that
+    // is on the nearest quantum boundary less than -(abs(x))
+    let quantum_bins = (beats.abs() + quantum) / quantum;
+    rem(beats + quantum_bins * quantum, quantum)
+}
+
+/// Returns the least value greater than x, that matches the phase of target with respect
+/// to the given quantum. Returns x, if the quantum is zero.
+fn next_phase_match(x: i64, target: i64, quantum: i64) -> i64 {
+    let desired_phase = phase(target, quantum);
+    let x_phase = phase(x, quantum);
+    let phase_diff = rem(desired_phase - x_phase + quantum, quantum);
+    x + phase_diff
+}
+
+/// Returns the closest value to x, that matches the phase of target with respect to the
+/// given quantum. Returns x, if the quantum is zero.
+fn closest_phase_match(x: i64, target: i64, quantum: i64) -> i64 {
+    next_phase_match(x - to_micro_beats(0.5 * to_beats(quantum)), target, quantum)
+}
+
+/// The beat/time mapping of a Link session at a constant tempo, as held by a
+/// [SessionState](crate::SessionState).
+///
+/// A Timeline reimplements the timeline math of Link in pure Rust, so it can be used for
+/// planning, rendering or previews without a live session. All functions give the same
+/// results as the functions of the same name on a SessionState with the same timeline.
+/// The timeline of a SessionState can be copied with
+/// [SessionSnapshot::timeline](crate::SessionSnapshot::timeline).
+///
+/// With the `serde` feature, Timeline implements `Serialize` and `Deserialize`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Timeline {
+    pub(crate) tempo: f64,
+    // In microbeats.
+    pub(crate) beat_origin: i64,
+    pub(crate) time_origin: i64,
+}
+
+impl Timeline {
+    /// Create a timeline, which is at `beat_origin` at `time_origin` and progresses at
+    /// the given tempo.
+    ///
+    ///  Returns [Error::InvalidTempo] or [Error::InvalidBeat], if the tempo is not finite
+    ///  or outside of the range Link supports, or the beat origin is not finite.
+    pub fn new(tempo: f64, beat_origin: f64, time_origin: i64) -> Result<Timeline, Error> {
+        Ok(Timeline {
+            tempo: error::validate_tempo(tempo)?,
+            beat_origin: to_micro_beats(error::validate_beat(beat_origin)?),
+            time_origin,
+        })
+    }
+
+    /// The tempo of the timeline, in Beats Per Minute.
+    pub fn tempo(&self) -> f64 {
+        self.tempo
+    }
+
+    /// The beat at the time origin of the timeline.
+    pub fn beat_origin(&self) -> f64 {
+        to_beats(self.beat_origin)
+    }
+
+    /// The time in microseconds, at which the timeline is at its beat origin.
+    pub fn time_origin(&self) -> i64 {
+        self.time_origin
+    }
+
+    ///  Set the timeline tempo to the given bpm value, taking effect at the given time.
+    ///  See [SessionState::set_tempo](crate::SessionState::set_tempo).
+    ///
+    ///  Like Link, this clamps the tempo to the range from [MIN_TEMPO] to [MAX_TEMPO].
+    pub fn set_tempo(&mut self, bpm: f64, at_time: i64) {
+        self.set_tempo_unchecked(bpm.clamp(MIN_TEMPO, MAX_TEMPO), at_time);
+    }
+
+    ///  Get the beat value corresponding to the given time for the given quantum.
+    ///  See [SessionState::beat_at_time](crate::SessionState::beat_at_time).
+    pub fn beat_at_time(&self, time: i64, quantum: f64) -> f64 {
+        to_beats(self.phase_encoded_beats_at(time, to_micro_beats(quantum)))
+    }
+
+    /// Get the session phase at the given time for the given quantum.
+    /// See [SessionState::phase_at_time](crate::SessionState::phase_at_time).
+    pub fn phase_at_time(&self, time: i64, quantum: f64) -> f64 {
+        let quantum = to_micro_beats(quantum);
+        to_beats(phase(self.phase_encoded_beats_at(time, quantum), quantum))
+    }
+
+    ///  Get the time at which the given beat occurs for the given quantum.
+    ///  See [SessionState::time_at_beat](crate::SessionState::time_at_beat).
+    pub fn time_at_beat(&self, beat: f64, quantum: f64) -> i64 {
+        self.time_of_phase_encoded_beats(to_micro_beats(beat), to_micro_beats(quantum))
+    }
+
+    ///  Map the given beat to the next time value at or after the given time, which has
+    ///  the same phase as the given beat.
+    ///
+    ///  This is the rule [SessionState::request_beat_at_time](crate::SessionState::request_beat_at_time)
+    ///  follows, while other peers are connected. Without other peers, abl_link
+    ///  follows the rule of [Timeline::force_beat_at_time] instead.
+    pub fn request_beat_at_time(&mut self, beat: f64, time: i64, quantum: f64) {
+        self.request_micro_beat_at_time(to_micro_beats(beat), time, to_micro_beats(quantum));
+    }
+
+    /// Unconditionally map the given beat to the given time.
+    /// See [SessionState::force_beat_at_time](crate::SessionState::force_beat_at_time).
+    pub fn force_beat_at_time(&mut self, beat: f64, time: i64, quantum: f64) {
+        self.force_micro_beat_at_time(to_micro_beats(beat), time, to_micro_beats(quantum));
+    }
+
+    // Typed variants of the functions above, which can not be called with invalid values.
+
+    /// The tempo of the timeline. See [Timeline::tempo].
+    pub fn tempo_bpm(&self) -> Bpm {
+        Bpm::new_unchecked(self.tempo)
+    }
+
+    /// Set the timeline tempo, taking effect at the given time. See [Timeline::set_tempo].
+    pub fn set_tempo_bpm(&mut self, bpm: Bpm, at_time: Micros) {
+        self.set_tempo_unchecked(bpm.get(), at_time.get());
+    }
+
+    /// Get the beat value corresponding to the given time for the given quantum.
+    /// See [Timeline::beat_at_time].
+    pub fn beat_at(&self, time: Micros, quantum: Quantum) -> Beats {
+        let quantum = to_micro_beats(quantum.get());
+        Beats::new_unchecked(to_beats(self.phase_encoded_beats_at(time.get(), quantum)))
+    }
+
+    /// Get the session phase at the given time for the given quantum.
+    /// See [Timeline::phase_at_time].
+    pub fn phase_at(&self, time: Micros, quantum: Quantum) -> Beats {
+        Beats::new_unchecked(self.phase_at_time(time.get(), quantum.get()))
+    }
+
+    /// Get the time at which the given beat occurs for the given quantum.
+    /// See [Timeline::time_at_beat].
+    pub fn time_at(&self, beat: Beats, quantum: Quantum) -> Micros {
+        Micros::new(self.time_at_beat(beat.get(), quantum.get()))
+    }
+
+    /// Map the given beat to the next time value with the same phase.
+    /// See [Timeline::request_beat_at_time].
+    pub fn request_beat_at(&mut self, beat: Beats, time: Micros, quantum: Quantum) {
+        self.request_micro_beat_at_time(
+            to_micro_beats(beat.get()),
+            time.get(),
+            to_micro_beats(quantum.get()),
+        );
+    }
+
+    /// Unconditionally map the given beat to the given time.
+    /// See [Timeline::force_beat_at_time].
+    pub fn force_beat_at(&mut self, beat: Beats, time: Micros, quantum: Quantum) {
+        self.force_beat_at_time(beat.get(), time.get(), quantum.get());
+    }
+
+    // Truncated like the duration_cast in Link's Tempo::microsPerBeat.
+    fn micros_per_beat(&self) -> i64 {
+        (60e6 / self.tempo) as i64
+    }
+
+    // The beat in microbeats at the given time, without any quantization.
+    fn beats_at(&self, time: i64) -> i64 {
+        let beats = (time - self.time_origin) as f64 / self.micros_per_beat() as f64;
+        self.beat_origin + to_micro_beats(beats)
+    }
+
+    // The time of the given beat in microbeats, without any quantization.
+    fn time_of(&self, beats: i64) -> i64 {
+        let beats = to_beats(beats - self.beat_origin);
+        self.time_origin + (beats * self.micros_per_beat() as f64).round() as i64
+    }
+
+    fn phase_encoded_beats_at(&self, time: i64, quantum: i64) -> i64 {
+        let beat = self.beats_at(time);
+        closest_phase_match(beat, beat - self.beat_origin, quantum)
+    }
+
+    fn time_of_phase_encoded_beats(&self, beat: i64, quantum: i64) -> i64 {
+        let from_origin = beat - self.beat_origin;
+        let origin_offset = from_origin - phase(from_origin, quantum);
+        // Invert the phase calculation, so that the phase of the resulting beat matches
+        // the given beat.
+        let inverse_phase_offset = closest_phase_match(
+            quantum - phase(from_origin, quantum),
+            quantum - phase(beat, quantum),
+            quantum,
+        );
+        self.time_of(self.beat_origin + origin_offset + quantum - inverse_phase_offset)
+    }
+
+    fn set_tempo_unchecked(&mut self, bpm: f64, at_time: i64) {
+        let desired = Timeline {
+            tempo: bpm,
+            beat_origin: self.beats_at(at_time),
+            time_origin: at_time,
+        };
+        self.tempo = bpm;
+        self.time_origin = desired.time_of(self.beat_origin);
+    }
+
+    fn request_micro_beat_at_time(&mut self, beat: i64, time: i64, quantum: i64) {
+        let next = next_phase_match(self.phase_encoded_beats_at(time, quantum), beat, quantum);
+        let time = self.time_of_phase_encoded_beats(next, quantum);
+        self.force_micro_beat_at_time(beat, time, quantum);
+    }
+
+    fn force_micro_beat_at_time(&mut self, beat: i64, time: i64, quantum: i64) {
+        // There are two components to the beat adjustment: a phase shift and a beat
+        // magnitude adjustment.
+        let current = self.phase_encoded_beats_at(time, quantum);
+        let closest_in_phase = closest_phase_match(current, beat, quantum);
+        let shift = closest_in_phase - current;
+        self.time_origin -= self.time_of(shift) - self.time_of(0);
+        self.beat_origin += beat - closest_in_phase;
+    }
+}
@@ -0,0 +1,448 @@
+use crate::{Error, error};
+use std::{
+    ops::{Add, AddAssign, Neg, Sub, SubAssign},
+    time::Duration,
+};
+
+/// A time on the Link clock, or a difference between two such times, in microseconds.
+///
+/// This is the unit of [AblLink::clock_micros](crate::AblLink::clock_micros) and of all
+/// times in a [SessionState](crate::SessionState). Arithmetic panics on overflow.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Micros(i64);
+
+impl Micros {
+    pub const ZERO: Micros = Micros(0);
+
+    pub fn new(micros: i64) -> Self {
+        Self(micros)
+    }
+
+    pub fn get(self) -> i64 {
+        self.0
+    }
+
+    /// Converts a [Duration] to Micros. Returns [Error::TimeOutOfRange], if it does not
+    /// fit into an i64 of microseconds.
+    pub fn from_duration(duration: Duration) -> Result<Self, Error> {
+        i64::try_from(duration.as_micros())
+            .map(Self)
+            .map_err(|_| Error::TimeOutOfRange)
+    }
+
+    /// Converts to a [Duration]. Returns [Error::TimeOutOfRange], if the value is negative.
+    pub fn to_duration(self) -> Result<Duration, Error> {
+        u64::try_from(self.0)
+            .map(Duration::from_micros)
+            .map_err(|_| Error::TimeOutOfRange)
+    }
+
+    pub fn checked_add(self, rhs: Micros) -> Option<Micros> {
+        self.0.checked_add(rhs.0).map(Self)
+    }
+
+    pub fn checked_sub(self, rhs: Micros) -> Option<Micros> {
+        self.0.checked_sub(rhs.0).map(Self)
+    }
+}
+
+impl From<i64> for Micros {
+    fn from(micros: i64) -> Self {
+        Self(micros)
+    }
+}
+
+impl From<Micros> for i64 {
+    fn from(micros: Micros) -> Self {
+        micros.0
+    }
+}
+
+impl TryFrom<Duration> for Micros {
+    type Error = Error;
+
+    fn try_from(duration: Duration) -> Result<Self, Error> {
+        Self::from_duration(duration)
+    }
+}
+
+impl TryFrom<Micros> for Duration {
+    type Error = Error;
+
+    fn try_from(micros: Micros) -> Result<Self, Error> {
+        micros.to_duration()
+    }
+}
+
+impl Add for Micros {
+    type Output = Micros;
+
+    fn add(self, rhs: Micros) -> Micros {
+        self.checked_add(rhs).expect("overflow when adding Micros")
+    }
+}
+
+impl Sub for Micros {
+    type Output = Micros;
+
+    fn sub(self, rhs: Micros) -> Micros {
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting Micros")
+    }
+}
+
+impl Neg for Micros {
+    type Output = Micros;
+
+    fn neg(self) -> Micros {
+        Micros(self.0.checked_neg().expect("overflow when negating Micros"))
+    }
+}
+
+impl Add<Duration> for Micros {
+    type Output = Micros;
+
+    fn add(self, rhs: Duration) -> Micros {
+        self + Micros::from_duration(rhs).expect("overflow when adding Duration to Micros")
+    }
+}
+
+impl Sub<Duration> for Micros {
+    type Output = Micros;
+
+    fn sub(self, rhs: Duration) -> Micros {
+        self - Micros::from_duration(rhs).expect("overflow when subtracting Duration from Micros")
+    }
+}
+
+impl AddAssign for Micros {
+    fn add_assign(&mut self, rhs: Micros) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Micros {
+    fn sub_assign(&mut self, rhs: Micros) {
+        *self = *self - rhs;
+    }
+}
+
+impl AddAssign<Duration> for Micros {
+    fn add_assign(&mut self, rhs: Duration) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign<Duration> for Micros {
+    fn sub_assign(&mut self, rhs: Duration) {
+        *self = *self - rhs;
+    }
+}
+
+/// A beat value on a Link timeline, or a difference between two beat values.
+///
+/// The value is always finite. Arithmetic panics, if the result is not.
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct Beats(f64);
+
+impl Beats {
+    pub const ZERO: Beats = Beats(0.);
+
+    /// Returns [Error::InvalidBeat], if the value is not finite.
+    pub fn new(beats: f64) -> Result<Self, Error> {
+        error::validate_beat(beats).map(Self)
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    pub fn checked_add(self, rhs: Beats) -> Option<Beats> {
+        Self::new(self.0 + rhs.0).ok()
+    }
+
+    pub fn checked_sub(self, rhs: Beats) -> Option<Beats> {
+        Self::new(self.0 - rhs.0).ok()
+    }
+
+    /// For values returned by Link, which are always valid.
+    pub(crate) fn new_unchecked(beats: f64) -> Self {
+        Self(beats)
+    }
+}
+
+impl TryFrom<f64> for Beats {
+    type Error = Error;
+
+    fn try_from(beats: f64) -> Result<Self, Error> {
+        Self::new(beats)
+    }
+}
+
+impl From<Beats> for f64 {
+    fn from(beats: Beats) -> Self {
+        beats.0
+    }
+}
+
+impl Add for Beats {
+    type Output = Beats;
+
+    fn add(self, rhs: Beats) -> Beats {
+        self.checked_add(rhs).expect("overflow when adding Beats")
+    }
+}
+
+impl Sub for Beats {
+    type Output = Beats;
+
+    fn sub(self, rhs: Beats) -> Beats {
+        self.checked_sub(rhs)
+            .expect("overflow when subtracting Beats")
+    }
+}
+
+impl Neg for Beats {
+    type Output = Beats;
+
+    fn neg(self) -> Beats {
+        Beats(-self.0)
+    }
+}
+
+impl AddAssign for Beats {
+    fn add_assign(&mut self, rhs: Beats) {
+        *self = *self + rhs;
+    }
+}
+
+impl SubAssign for Beats {
+    fn sub_assign(&mut self, rhs: Beats) {
+        *self = *self - rhs;
+    }
+}
+
+/// The number of beats in a bar or loop, which Link keeps in phase between peers.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Quantum(f64);
+
+impl Quantum {
+    /// Returns [Error::InvalidQuantum], if the value is not finite or negative.
+    pub fn new(quantum: f64) -> Result<Self, Error> {
+        error::validate_quantum(quantum).map(Self)
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    /// The quantum as a number of [Beats].
+    pub fn beats(self) -> Beats {
+        Beats(self.0)
+    }
+}
+
+impl TryFrom<f64> for Quantum {
+    type Error = Error;
+
+    fn try_from(quantum: f64) -> Result<Self, Error> {
+        Self::new(quantum)
+    }
+}
+
+impl From<Quantum> for f64 {
+    fn from(quantum: Quantum) -> Self {
+        quantum.0
+    }
+}
+
+/// A tempo in Beats Per Minute, within the range Link supports.
+#[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
+pub struct Bpm(f64);
+
+impl Bpm {
+    /// Returns [Error::InvalidTempo], if the value is not finite, or outside of the range
+    /// from [MIN_TEMPO](crate::MIN_TEMPO) to [MAX_TEMPO](crate::MAX_TEMPO).
+    pub fn new(bpm: f64) -> Result<Self, Error> {
+        error::validate_tempo(bpm).map(Self)
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+
+    /// The number of beats, which pass in the given time at this tempo.
+    pub fn beats_in(self, time: Micros) -> Beats {
+        Beats(time.0 as f64 * self.0 / 60e6)
+    }
+
+    /// The time, which the given number of beats take at this tempo. Panics, if it does
+    /// not fit into an i64 of microseconds.
+    pub fn time_of(self, beats: Beats) -> Micros {
+        self.checked_time_of(beats)
+            .expect("overflow when converting Beats to Micros")
+    }
+
+    /// Like [time_of](Bpm::time_of), but returns None instead of panicking.
+    pub fn checked_time_of(self, beats: Beats) -> Option<Micros> {
+        let micros = (beats.0 * 60e6 / self.0).round();
+        // i64::MIN is exactly representable as f64, i64::MAX is not, so compare against
+        // -i64::MIN, which is the first value out of range.
+        (micros >= i64::MIN as f64 && micros < -(i64::MIN as f64)).then_some(Micros(micros as i64))
+    }
+
+    /// For values returned by Link, which are always valid.
+    pub(crate) fn new_unchecked(bpm: f64) -> Self {
+        Self(bpm)
+    }
+}
+
+impl TryFrom<f64> for Bpm {
+    type Error = Error;
+
+    fn try_from(bpm: f64) -> Result<Self, Error> {
+        Self::new(bpm)
+    }
+}
+
+impl From<Bpm> for f64 {
+    fn from(bpm: Bpm) -> Self {
+        bpm.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MAX_TEMPO, MIN_TEMPO};
+
+    #[test]
+    #[should_panic(expected = "overflow when adding Micros")]
+    fn micros_add_panics_on_overflow() {
+        let _ = Micros::new(i64::MAX) + Micros::new(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when subtracting Micros")]
+    fn micros_sub_panics_on_overflow() {
+        let _ = Micros::new(i64::MIN) - Micros::new(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when negating Micros")]
+    fn micros_neg_panics_at_min() {
+        let _ = -Micros::new(i64::MIN);
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when adding Duration to Micros")]
+    fn micros_add_duration_panics_on_overflow() {
+        let _ = Micros::ZERO + Duration::MAX;
+    }
+
+    #[test]
+    fn micros_checked_arithmetic() {
+        assert_eq!(-Micros::new(i64::MAX), Micros::new(-i64::MAX));
+        assert_eq!(Micros::new(i64::MAX).checked_add(Micros::new(1)), None);
+        assert_eq!(Micros::new(i64::MIN).checked_sub(Micros::new(1)), None);
+        assert_eq!(
+            Micros::new(i64::MAX - 1).checked_add(Micros::new(1)),
+            Some(Micros::new(i64::MAX))
+        );
+    }
+
+    #[test]
+    fn micros_duration_round_trip() {
+        for micros in [0, 1, 1_000_000, i64::MAX] {
+            let duration = Micros::new(micros).to_duration().unwrap();
+            assert_eq!(duration.as_micros(), micros as u128);
+            assert_eq!(Micros::try_from(duration), Ok(Micros::new(micros)));
+        }
+        // Sub-microsecond parts are truncated.
+        assert_eq!(
+            Micros::from_duration(Duration::from_nanos(1_999)),
+            Ok(Micros::new(1))
+        );
+        assert_eq!(Micros::new(-1).to_duration(), Err(Error::TimeOutOfRange));
+        assert_eq!(
+            Micros::from_duration(Duration::MAX),
+            Err(Error::TimeOutOfRange)
+        );
+    }
+
+    #[test]
+    fn beats_must_be_finite() {
+        for beats in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(Beats::new(beats), Err(Error::InvalidBeat(_))));
+        }
+        assert_eq!(Beats::new(f64::MAX).map(f64::from), Ok(f64::MAX));
+    }
+
+    #[test]
+    fn beats_checked_arithmetic_returns_none_on_overflow() {
+        let max = Beats::new(f64::MAX).unwrap();
+        assert_eq!(max.checked_add(max), None);
+        assert_eq!((-max).checked_sub(max), None);
+        assert_eq!(max.checked_sub(max), Some(Beats::ZERO));
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when adding Beats")]
+    fn beats_add_panics_on_overflow() {
+        let max = Beats::new(f64::MAX).unwrap();
+        let _ = max + max;
+    }
+
+    #[test]
+    fn quantum_and_bpm_are_validated() {
+        assert!(matches!(Quantum::new(-1.), Err(Error::InvalidQuantum(_))));
+        assert!(matches!(
+            Quantum::new(f64::NAN),
+            Err(Error::InvalidQuantum(_))
+        ));
+        assert_eq!(Quantum::new(0.).map(f64::from), Ok(0.));
+        assert!(matches!(Bpm::new(f64::NAN), Err(Error::InvalidTempo(_))));
+        assert!(matches!(
+            Bpm::new(MAX_TEMPO + 1.),
+            Err(Error::InvalidTempo(_))
+        ));
+        assert_eq!(Bpm::new(MIN_TEMPO).map(f64::from), Ok(MIN_TEMPO));
+    }
+
+    #[test]
+    fn bpm_converts_between_beats_and_time() {
+        let bpm = Bpm::new(120.).unwrap();
+        assert_eq!(
+            bpm.beats_in(Micros::new(1_500_000)),
+            Beats::new(3.).unwrap()
+        );
+        assert_eq!(bpm.time_of(Beats::new(3.).unwrap()), Micros::new(1_500_000));
+        assert_eq!(
+            bpm.time_of(Beats::new(-0.5).unwrap()),
+            Micros::new(-250_000)
+        );
+    }
+
+    #[test]
+    fn bpm_checked_time_of_returns_none_out_of_range() {
+        let bpm = Bpm::new(MIN_TEMPO).unwrap();
+        let beats = |b: f64| Beats::new(b).unwrap();
+        assert_eq!(bpm.checked_time_of(beats(f64::MAX)), None);
+        assert_eq!(bpm.checked_time_of(beats(-f64::MAX)), None);
+        // 2^63 micros is the first value out of range, -2^63 the last one in range.
+        let per_micro = MIN_TEMPO / 60e6;
+        assert_eq!(bpm.checked_time_of(beats(2f64.powi(63) * per_micro)), None);
+        assert_eq!(
+            bpm.checked_time_of(beats(-(2f64.powi(63)) * per_micro)),
+            Some(Micros::new(i64::MIN))
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "overflow when converting Beats to Micros")]
+    fn bpm_time_of_panics_out_of_range() {
+        let _ = Bpm::new(MIN_TEMPO)
+            .unwrap()
+            .time_of(Beats::new(f64::MAX).unwrap());
+    }
+}
@@ -0,0 +1,60 @@
+// Feeds the host time estimators with a synthetic audio device, like the host_time_jitter
+// example, whose latency jumps by 2 ms, and checks how fast they follow the jump.
+
+#![cfg(feature = "test-util")]
+
+use rusty_link::{
+    DelayLockedLoop, HostTimeEstimator, HostTimeFilter, KalmanFilter, ManualClock,
+    test_util::{SyntheticDevice, SyntheticDeviceConfig},
+};
+
+const SAMPLE_RATE: f64 = 48_000.;
+const JUMP_SECONDS: f64 = 20.;
+const SECONDS: f64 = 25.;
+// The part of the run after the jump, in which the estimators are compared
+const COMPARED_SECONDS: (f64, f64) = (JUMP_SECONDS + 1.5, JUMP_SECONDS + 2.);
+
+/// The errors of the estimator against the true host time in µs, for each callback in
+/// COMPARED_SECONDS.
+fn compared_errors(mut estimator: Box<dyn HostTimeEstimator>) -> Vec<f64> {
+    let device = SyntheticDevice::new(SyntheticDeviceConfig {
+        sample_rate: SAMPLE_RATE,
+        jump_micros: 2000.,
+        jump_seconds: JUMP_SECONDS,
+        ..Default::default()
+    });
+    let clock = ManualClock::new(0);
+
+    let mut errors = Vec::new();
+    for callback in device.take_while(|callback| callback.seconds < SECONDS) {
+        clock.set(callback.clock_micros);
+        let estimate = estimator.sample_time_to_host_time_with(&clock, callback.sample_clock);
+        if (COMPARED_SECONDS.0..COMPARED_SECONDS.1).contains(&callback.seconds) {
+            errors.push(estimate as f64 - callback.true_micros);
+        }
+    }
+    errors
+}
+
+#[test]
+fn loops_follow_a_latency_jump_before_the_regression() {
+    for (name, estimator) in [
+        (
+            "dll",
+            Box::new(DelayLockedLoop::new(SAMPLE_RATE)) as Box<dyn HostTimeEstimator>,
+        ),
+        ("kalman", Box::new(KalmanFilter::new(SAMPLE_RATE))),
+    ] {
+        let errors = compared_errors(estimator);
+        let max = errors.iter().fold(0f64, |max, error| max.max(error.abs()));
+        assert!(max < 200., "{name} is {max} µs off after the jump");
+    }
+
+    // The regression still averages over points before the jump.
+    let errors = compared_errors(Box::new(HostTimeFilter::new()));
+    let mean = errors.iter().sum::<f64>() / errors.len() as f64;
+    assert!(
+        mean.abs() > 400.,
+        "regression is only {mean} µs off after the jump"
+    );
+}
@@ -0,0 +1,138 @@
+// Compares the HostTimeFilter, which updates its regression sums with each point, with
+// the batch regression over the whole buffer, which the filter used before.
+
+use proptest::prelude::*;
+use rusty_link::{FilterWindow, HostTimeFilter, HostTimeFilterConfig, OutlierRejection};
+
+// The window of HostTimeFilter::new
+const WINDOW: usize = 512;
+
+/// The HostTimeFilter before the running sums.
+struct BatchFilter {
+    points_buffer: Vec<(u128, u128)>,
+    max_buffer_size: usize,
+    index: usize,
+}
+
+impl BatchFilter {
+    fn new(max_buffer_size: usize) -> Self {
+        Self {
+            points_buffer: Vec::with_capacity(max_buffer_size),
+            max_buffer_size,
+            index: 0,
+        }
+    }
+
+    fn sample_time_to_host_time(&mut self, clock_micros: i64, sample_clock: u64) -> i64 {
+        assert!(clock_micros >= 0, "Negative clock values unsupported.");
+
+        let point = (sample_clock as u128, clock_micros as u128);
+        if self.points_buffer.len() < self.max_buffer_size {
+            self.points_buffer.push(point);
+        } else {
+            self.points_buffer[self.index] = point;
+        }
+        self.index = (self.index + 1) % self.max_buffer_size;
+
+        let (slope, intercept) = linear_regression(&self.points_buffer);
+        (slope * sample_clock as f64 + intercept).round() as i64
+    }
+}
+
+// The numerator of the slope is the only difference with negative covariance, which
+// would underflow the u128 of the original.
+fn linear_regression(buffer: &[(u128, u128)]) -> (f64, f64) {
+    let num_points = buffer.len() as u128;
+
+    let mut sum_x: u128 = 0;
+    let mut sum_xx: u128 = 0;
+    let mut sum_xy: u128 = 0;
+    let mut sum_y: u128 = 0;
+
+    for &(x, y) in buffer {
+        sum_x += x;
+        sum_xx += x * x;
+        sum_xy += x * y;
+        sum_y += y;
+    }
+
+    let denominator = num_points * sum_xx - sum_x * sum_x;
+
+    let slope = match denominator {
+        0 => 0.0,
+        _ => ((num_points * sum_xy) as i128 - (sum_x * sum_y) as i128) as f64 / denominator as f64,
+    };
+
+    let intercept = (sum_y as f64 - slope * sum_x as f64) / num_points as f64;
+
+    (slope, intercept)
+}
+
+// A step of the audio clock: the buffer size in samples and the jitter of the host time
+// in µs.
+fn step() -> impl Strategy<Value = (u64, i64)> {
+    (1u64..4096, -500i64..500)
+}
+
+proptest! {
+    // Long enough runs to wrap around the window, but few, as the batch regression is slow.
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn matches_the_batch_regression(
+        sample_rate in 8_000u32..192_000,
+        start_sample in 0u64..10_000_000_000,
+        start_host in 1_000_000i64..100_000_000_000,
+        steps in prop::collection::vec(step(), 1..3 * WINDOW),
+    ) {
+        let mut filter = HostTimeFilter::new();
+        let mut batch = BatchFilter::new(WINDOW);
+        compare(&mut filter, &mut batch, sample_rate, start_sample, start_host, steps)?;
+    }
+
+    // Small windows wrap around often, and include the degenerate lines through one and
+    // two points.
+    #[test]
+    fn matches_the_batch_regression_with_small_windows(
+        window in 1usize..20,
+        sample_rate in 8_000u32..192_000,
+        start_sample in 0u64..10_000_000_000,
+        start_host in 1_000_000i64..100_000_000_000,
+        steps in prop::collection::vec(step(), 1..100),
+    ) {
+        let mut filter = HostTimeFilter::with_config(HostTimeFilterConfig {
+            window: FilterWindow::Points(window),
+            outlier_rejection: OutlierRejection::Off,
+            ..Default::default()
+        });
+        let mut batch = BatchFilter::new(window);
+        compare(&mut filter, &mut batch, sample_rate, start_sample, start_host, steps)?;
+    }
+}
+
+fn compare(
+    filter: &mut HostTimeFilter,
+    batch: &mut BatchFilter,
+    sample_rate: u32,
+    start_sample: u64,
+    start_host: i64,
+    steps: Vec<(u64, i64)>,
+) -> Result<(), TestCaseError> {
+    let mut samples = 0;
+    for (buffer_size, jitter) in steps {
+        samples += buffer_size;
+        let host = start_host + (samples as f64 * 1e6 / sample_rate as f64) as i64 + jitter;
+        let sample_clock = start_sample + samples;
+
+        let filtered = filter.sample_time_to_host_time(host, sample_clock);
+        let expected = batch.sample_time_to_host_time(host, sample_clock);
+        // The batch regression rounds its f64 sums of absolute clock values.
+        prop_assert!(
+            (filtered - expected).abs() <= 1,
+            "filtered {} expected {}",
+            filtered,
+            expected
+        );
+    }
+    Ok(())
+}
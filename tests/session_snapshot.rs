@@ -0,0 +1,37 @@
+// Serializes SessionSnapshots and Timelines and reads them back in.
+
+#![cfg(feature = "serde")]
+
+use rusty_link::{SessionSnapshot, SessionState, Timeline};
+
+fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(value: &T) -> T {
+    let json = serde_json::to_string(value).expect("serialize");
+    serde_json::from_str(&json).expect("deserialize")
+}
+
+#[test]
+fn snapshot_round_trip() {
+    let mut session_state = SessionState::new();
+    session_state.set_tempo(133.7, 1_000_000);
+    session_state.force_beat_at_time(-3.25, 2_500_000, 4.);
+    session_state.set_is_playing(true, 4_000_000);
+    let snapshot = session_state.snapshot();
+
+    let copy: SessionSnapshot = round_trip(&snapshot);
+    assert_eq!(copy, snapshot);
+    assert_eq!(
+        copy.beat_at_time(10_000_000, 4.),
+        snapshot.beat_at_time(10_000_000, 4.)
+    );
+    assert_eq!(copy.time_for_is_playing(), 4_000_000);
+}
+
+#[test]
+fn timeline_round_trip() {
+    let mut timeline = SessionState::new().snapshot().timeline();
+    timeline.set_tempo(87.5, -7_000_000);
+    timeline.force_beat_at_time(1e6 / 3., 123_456_789, 3.);
+
+    let copy: Timeline = round_trip(&timeline);
+    assert_eq!(copy, timeline);
+}
@@ -0,0 +1,161 @@
+// Tests of the test-util helpers. The tests with several peers run on a SimulatedSession,
+// and again with AblLink peers. Those need a network interface with multicast support and
+// no other Link peers on the network, so they are ignored by default. Run them with:
+//   cargo test --features test-util --test test_util -- --include-ignored
+
+#![cfg(feature = "test-util")]
+
+use rusty_link::{
+    Clock, LinkBackend, SessionStateOps, SimulatedLink, SimulatedSession, SimulationConfig,
+    test_util::{PeerGroup, wait_until},
+};
+use std::time::Duration;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+const SIMULATION: SimulationConfig = SimulationConfig {
+    join_delay: Duration::from_millis(100),
+    leave_delay: Duration::from_millis(100),
+    tempo_delay: Duration::from_millis(20),
+    start_stop_delay: Duration::from_millis(30),
+};
+
+fn simulated(num_peers: usize) -> PeerGroup<SimulatedLink> {
+    PeerGroup::simulated(&SimulatedSession::new(SIMULATION), num_peers, 120.).unwrap()
+}
+
+fn networked(num_peers: usize) -> PeerGroup {
+    PeerGroup::new(num_peers, 120.).unwrap()
+}
+
+fn peers_converge<L: LinkBackend>(group: PeerGroup<L>) {
+    group.assert_peers_converge(TIMEOUT);
+    assert_eq!(group.num_peers(), vec![2, 2, 2]);
+}
+
+fn tempo_propagates<L: LinkBackend>(group: PeerGroup<L>) {
+    group.assert_peers_converge(TIMEOUT);
+    group.assert_tempo_propagates(1, 133., TIMEOUT);
+    group.assert_tempo_propagates(2, 87.5, TIMEOUT);
+}
+
+fn start_stop_propagates<L: LinkBackend>(group: PeerGroup<L>) {
+    group.enable_start_stop_sync(true);
+    group.assert_peers_converge(TIMEOUT);
+    group.assert_start_stop_propagates(0, true, TIMEOUT);
+    group.assert_start_stop_propagates(1, false, TIMEOUT);
+}
+
+fn phases_agree<L: LinkBackend>(group: PeerGroup<L>) {
+    group.assert_peers_converge(TIMEOUT);
+    group.assert_tempo_propagates(0, 140., TIMEOUT);
+    assert!(wait_until(TIMEOUT, || {
+        group
+            .phase_offsets_micros(4.)
+            .iter()
+            .all(|offset| offset.abs() < 1000)
+    }));
+    group.assert_phases_agree(4., 1000);
+}
+
+#[test]
+fn wait_until_returns_once_the_condition_is_true() {
+    let mut calls = 0;
+    assert!(wait_until(TIMEOUT, || {
+        calls += 1;
+        calls == 3
+    }));
+    assert_eq!(calls, 3);
+}
+
+#[test]
+fn wait_until_returns_false_after_the_timeout() {
+    assert!(!wait_until(Duration::from_millis(10), || false));
+}
+
+#[test]
+fn single_peer_passes_all_assertions() {
+    let group = PeerGroup::new(1, 120.).unwrap();
+    group.enable_start_stop_sync(true);
+
+    group.assert_tempo_propagates(0, 98., TIMEOUT);
+    group.assert_start_stop_propagates(0, true, TIMEOUT);
+    group.assert_phases_agree(4., 0);
+    assert_eq!(group.phase_offsets_micros(4.), vec![0]);
+}
+
+#[test]
+#[should_panic(expected = "did not propagate")]
+fn assert_tempo_propagates_panics_between_disabled_peers() {
+    let group = PeerGroup::new(2, 120.).unwrap();
+    for peer in group.peers() {
+        peer.enable(false);
+    }
+    group.assert_tempo_propagates(1, 60., Duration::from_millis(50));
+}
+
+#[test]
+fn simulated_peers_converge() {
+    peers_converge(simulated(3));
+}
+
+#[test]
+fn simulated_tempo_propagates() {
+    tempo_propagates(simulated(3));
+}
+
+#[test]
+fn simulated_start_stop_propagates() {
+    start_stop_propagates(simulated(2));
+}
+
+#[test]
+fn simulated_phases_agree() {
+    phases_agree(simulated(3));
+}
+
+#[test]
+fn simulated_peers_adopt_the_phase_of_the_session() {
+    let group = simulated(3);
+    // Shift the beat of the first peer, before the others join its session.
+    let mut session_state = Default::default();
+    group.peer(0).capture_app_session_state(&mut session_state);
+    session_state.force_beat_at_time(0.5, group.peer(0).clock_micros(), 4.);
+    group.peer(0).commit_app_session_state(&session_state);
+    assert_eq!(group.phase_offsets_micros(4.), vec![0, -250_000, -250_000]);
+
+    group.assert_peers_converge(TIMEOUT);
+    group.assert_phases_agree(4., 0);
+}
+
+#[test]
+#[should_panic(expected = "did not propagate")]
+fn simulated_tempo_does_not_propagate_faster_than_the_tempo_delay() {
+    let group = simulated(2);
+    group.assert_peers_converge(TIMEOUT);
+    group.assert_tempo_propagates(0, 60., SIMULATION.tempo_delay - Duration::from_millis(2));
+}
+
+#[test]
+#[ignore = "needs a network interface with multicast"]
+fn peers_converge_on_the_network() {
+    peers_converge(networked(3));
+}
+
+#[test]
+#[ignore = "needs a network interface with multicast"]
+fn tempo_propagates_on_the_network() {
+    tempo_propagates(networked(3));
+}
+
+#[test]
+#[ignore = "needs a network interface with multicast"]
+fn start_stop_propagates_on_the_network() {
+    start_stop_propagates(networked(2));
+}
+
+#[test]
+#[ignore = "needs a network interface with multicast"]
+fn phases_agree_on_the_network() {
+    phases_agree(networked(3));
+}
@@ -0,0 +1,123 @@
+// Compares the pure Rust Timeline with the timeline math of abl_link, by applying the
+// same random changes to a SessionState and a Timeline.
+//
+// A SessionState created with SessionState::new is not connected to any peers, so abl_link
+// handles request_beat_at_time like force_beat_at_time. Timeline::request_beat_at_time
+// follows the rule for connected peers, which can only be compared with a Link session.
+
+use proptest::prelude::*;
+use rusty_link::{SessionState, Timeline};
+
+#[derive(Clone, Debug)]
+enum Change {
+    SetTempo { bpm: f64, at_time: i64 },
+    RequestBeatAtTime { beat: f64, time: i64, quantum: f64 },
+    ForceBeatAtTime { beat: f64, time: i64, quantum: f64 },
+}
+
+fn tempo() -> impl Strategy<Value = f64> {
+    20.0..=999.0
+}
+
+// Zero, whole, fractional and odd quanta.
+fn quantum() -> impl Strategy<Value = f64> {
+    prop_oneof![
+        Just(0.),
+        (1u32..=16).prop_map(f64::from),
+        0.01..16.0,
+        Just(4. / 3.),
+    ]
+}
+
+fn time() -> impl Strategy<Value = i64> {
+    -20_000_000_000i64..20_000_000_000
+}
+
+fn beat() -> impl Strategy<Value = f64> {
+    -20_000.0..20_000.0
+}
+
+fn change() -> impl Strategy<Value = Change> {
+    prop_oneof![
+        (tempo(), time()).prop_map(|(bpm, at_time)| Change::SetTempo { bpm, at_time }),
+        (beat(), time(), quantum()).prop_map(|(beat, time, quantum)| {
+            Change::RequestBeatAtTime {
+                beat,
+                time,
+                quantum,
+            }
+        }),
+        (beat(), time(), quantum()).prop_map(|(beat, time, quantum)| {
+            Change::ForceBeatAtTime {
+                beat,
+                time,
+                quantum,
+            }
+        }),
+    ]
+}
+
+fn apply(session_state: &mut SessionState, timeline: &mut Timeline, change: &Change) {
+    match *change {
+        Change::SetTempo { bpm, at_time } => {
+            session_state.set_tempo(bpm, at_time);
+            timeline.set_tempo(bpm, at_time);
+        }
+        Change::RequestBeatAtTime {
+            beat,
+            time,
+            quantum,
+        } => {
+            session_state.request_beat_at_time(beat, time, quantum);
+            timeline.force_beat_at_time(beat, time, quantum);
+        }
+        Change::ForceBeatAtTime {
+            beat,
+            time,
+            quantum,
+        } => {
+            session_state.force_beat_at_time(beat, time, quantum);
+            timeline.force_beat_at_time(beat, time, quantum);
+        }
+    }
+}
+
+proptest! {
+    #[test]
+    fn changes_match_abl_link(changes in prop::collection::vec(change(), 1..8)) {
+        let mut session_state = SessionState::new();
+        let mut timeline = session_state.snapshot().timeline();
+        for change in &changes {
+            apply(&mut session_state, &mut timeline, change);
+            prop_assert_eq!(session_state.snapshot().timeline(), timeline);
+        }
+    }
+
+    #[test]
+    fn queries_match_abl_link(
+        changes in prop::collection::vec(change(), 0..4),
+        time in time(),
+        beat in beat(),
+        quantum in quantum(),
+    ) {
+        let mut session_state = SessionState::new();
+        let mut timeline = session_state.snapshot().timeline();
+        for change in &changes {
+            apply(&mut session_state, &mut timeline, change);
+        }
+
+        // Compare the bits, so -0. and 0. are told apart as well.
+        prop_assert_eq!(
+            timeline.beat_at_time(time, quantum).to_bits(),
+            session_state.beat_at_time(time, quantum).to_bits()
+        );
+        prop_assert_eq!(
+            timeline.phase_at_time(time, quantum).to_bits(),
+            session_state.phase_at_time(time, quantum).to_bits()
+        );
+        prop_assert_eq!(
+            timeline.time_at_beat(beat, quantum),
+            session_state.time_at_beat(beat, quantum)
+        );
+    }
+}